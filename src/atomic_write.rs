@@ -0,0 +1,78 @@
+use std::{
+    fs::{create_dir_all, remove_file, rename, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tokio::task::spawn_blocking;
+
+/// makes temp file names unique between writes in the same process
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file without ever leaving it truncated or half written
+/// The contents go to a temp file which is then renamed over the original, so readers
+/// always see a complete file. Hold a `FileLock` across reading and writing to keep
+/// other processes from overwriting each other's changes
+pub async fn atomic_write(
+    path: impl Into<PathBuf>,
+    contents: impl Into<Vec<u8>>,
+) -> io::Result<()> {
+    let path = path.into();
+    let contents = contents.into();
+    spawn_blocking(move || atomic_write_blocking(&path, &contents)).await?
+}
+
+fn atomic_write_blocking(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap();
+    create_dir_all(dir)?;
+
+    // unique per write so concurrent writers never share a temp file
+    let temp_path = path.with_extension(format!(
+        "tmp.{}.{}",
+        process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| rename(&temp_path, path));
+    if result.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    result
+}
+
+/// An advisory lock on `<path>.lock`, shared by all usaco processes. Released when dropped
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Wait until no other process holds the lock for `path`
+    /// Locks are per open file, so don't take the same lock twice in one process
+    pub async fn acquire(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        spawn_blocking(move || {
+            let mut name = path.file_name().unwrap().to_owned();
+            name.push(".lock");
+            let lock_path = path.with_file_name(name);
+            create_dir_all(lock_path.parent().unwrap())?;
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(lock_path)?;
+            match file.lock() {
+                // some filesystems can't lock. writes are still atomic
+                Err(e) if e.kind() == ErrorKind::Unsupported => {}
+                result => result?,
+            }
+            Ok(Self { _file: file })
+        })
+        .await?
+    }
+}
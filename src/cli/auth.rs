@@ -1,10 +1,13 @@
-use super::status_spinner::StatusSpinner;
+use super::{
+    output::{print_json, OutputFormat},
+    status_spinner::StatusSpinner,
+};
 use crate::{
     credential_storage::CredentialStorage,
-    http_client::{HttpClient, HttpClientError, UserInfo},
+    http_client::{HttpClient, HttpClientError, PasswordPrompt, UserInfo},
 };
 use clap::Subcommand;
-use console::style;
+use console::{style, user_attended};
 use dialoguer::{theme::ColorfulTheme, Input, Password};
 use indicatif::MultiProgress;
 use std::sync::Arc;
@@ -16,6 +19,9 @@ pub enum Command {
         /// Username of the account to log in to. Will prompt if not given
         #[arg(short, long)]
         username: Option<String>,
+        /// Only store the session ID. You will be asked for your password when the session expires
+        #[arg(short, long)]
+        session_only: bool,
     },
     /// Log out of your USACO account
     Logout,
@@ -23,11 +29,28 @@ pub enum Command {
     Whoami,
 }
 
+/// Prompt for the password again when a session-only login expires
+pub fn password_prompt(multi: MultiProgress) -> PasswordPrompt {
+    Arc::new(move |username| {
+        // can't prompt in scripts
+        if !user_attended() {
+            return None;
+        }
+        multi.suspend(|| {
+            Password::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Session expired. Password for {}", username))
+                .interact()
+                .ok()
+        })
+    })
+}
+
 pub async fn handle(
     command: Command,
     client: HttpClient,
     cred_storage: Arc<dyn CredentialStorage>,
     multi: MultiProgress,
+    format: OutputFormat,
 ) -> super::Result {
     match command {
         Command::Logout => {
@@ -35,7 +58,10 @@ pub async fn handle(
             cred_storage.clear_credentials().await?;
             status.finish("Logged out", true);
         }
-        Command::Login { username } => {
+        Command::Login {
+            username,
+            session_only,
+        } => {
             // make sure they're not already logged in
             if cred_storage.logged_in().await? {
                 StatusSpinner::new("", &multi).finish("You are already logged in!", false);
@@ -58,10 +84,15 @@ pub async fn handle(
                 let status = StatusSpinner::new("Logging in...", &multi);
 
                 // log in
-                match client.login(user_id, password).await {
+                match client.login(user_id, password, !session_only).await {
                     Ok(()) => {
                         status.finish("Successfully logged in.", true);
-                        if !cred_storage.is_secure() {
+                        if session_only {
+                            println!(
+                                "{}",
+                                style("Password not stored. You will be asked for it when your session expires.").dim()
+                            );
+                        } else if !cred_storage.is_secure() {
                             println!(
                                 "{} {}",
                                 style("⚠ Warning:").yellow().bold(),
@@ -82,6 +113,7 @@ pub async fn handle(
             let status = StatusSpinner::new("Loading account information...", &multi);
 
             match client.get_user_info().await {
+                Ok(info) if format == OutputFormat::Json => print_json(&info)?,
                 Ok(UserInfo {
                     first_name,
                     last_name,
@@ -119,6 +151,9 @@ pub async fn handle(
                 }
                 Err(HttpClientError::LoggedOut) => {
                     status.finish("You are not currently logged in.", false);
+                    if format == OutputFormat::Json {
+                        return Err(HttpClientError::LoggedOut.into());
+                    }
                 }
                 e => {
                     e?;
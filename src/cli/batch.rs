@@ -0,0 +1,226 @@
+use super::test_runner::{CaseResult, TestReport, Verdict};
+use crate::{http_client::Division, preferences::Language};
+use console::{style, StyledObject};
+use serde::Serialize;
+use std::{
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+use tokio::fs::read_dir;
+
+/// A solution file in the solutions directory
+#[derive(Debug)]
+pub struct SolutionFile {
+    pub division: Division,
+    pub problem_id: u64,
+    /// `brute` for `<id>.brute.cpp`
+    pub variant: Option<String>,
+    pub lang: Language,
+    pub path: PathBuf,
+}
+
+/// Find solutions in `src/<division>/<id>.<ext>`, optionally only from one division
+pub async fn find_solutions(
+    dir: &Path,
+    division: Option<Division>,
+) -> io::Result<Vec<SolutionFile>> {
+    let mut solutions = vec![];
+    for name in Division::get_all() {
+        let Some(d) = Division::from_str(name) else {
+            continue;
+        };
+        if division.is_some_and(|division| division != d) {
+            continue;
+        }
+        let mut entries = match read_dir(dir.join("src").join(name)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let lang = match path.extension().and_then(|e| e.to_str()) {
+                Some("cpp") => Language::CPP,
+                Some("py") => Language::Python,
+                _ => continue,
+            };
+            // the file name is the problem ID, optionally followed by a variant
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let (id, variant) = match stem.split_once('.') {
+                Some((id, variant)) => (id, Some(variant.to_string())),
+                None => (stem, None),
+            };
+            let Ok(problem_id) = id.parse() else {
+                continue;
+            };
+            solutions.push(SolutionFile {
+                division: d,
+                problem_id,
+                variant,
+                lang,
+                path,
+            });
+        }
+    }
+    // the main solution comes before its variants
+    solutions.sort_by(|a, b| {
+        (a.division, a.problem_id, &a.variant, a.lang).cmp(&(
+            b.division,
+            b.problem_id,
+            &b.variant,
+            b.lang,
+        ))
+    });
+    Ok(solutions)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Passed,
+    Failed,
+    /// the solution could not be tested, e.g. it didn't compile
+    Error,
+}
+
+/// The result of testing one solution file
+#[derive(Debug, Serialize)]
+pub struct BatchEntry {
+    pub problem_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    pub division: Division,
+    pub language: Language,
+    pub status: BatchStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub passed: usize,
+    pub total: usize,
+    pub cases: Vec<CaseResult>,
+}
+
+impl BatchEntry {
+    pub fn new(solution: &SolutionFile, result: Result<Vec<CaseResult>, String>) -> Self {
+        let (cases, error) = match result {
+            Ok(cases) => (cases, None),
+            Err(error) => (vec![], Some(error)),
+        };
+        let passed = cases
+            .iter()
+            .filter(|c| c.verdict == Verdict::Passed)
+            .count();
+        let status = if error.is_some() {
+            BatchStatus::Error
+        } else if passed == cases.len() {
+            BatchStatus::Passed
+        } else {
+            BatchStatus::Failed
+        };
+        Self {
+            problem_id: solution.problem_id,
+            variant: solution.variant.clone(),
+            division: solution.division,
+            language: solution.lang,
+            status,
+            error,
+            passed,
+            total: cases.len(),
+            cases,
+        }
+    }
+
+    /// Report for this solution, including why it couldn't be tested
+    pub fn report(&self, official_data: bool) -> TestReport<'_> {
+        let mut report = TestReport::new(self.problem_id, official_data, &self.cases);
+        report.error = self.error.as_deref();
+        report
+    }
+}
+
+/// Results of `solution test --all`, as printed by `--format json`
+#[derive(Debug, Serialize)]
+pub struct BatchReport<'a> {
+    pub passed: usize,
+    pub total: usize,
+    pub solutions: &'a [BatchEntry],
+}
+
+impl<'a> BatchReport<'a> {
+    pub fn new(solutions: &'a [BatchEntry]) -> Self {
+        Self {
+            passed: solutions
+                .iter()
+                .filter(|s| s.status == BatchStatus::Passed)
+                .count(),
+            total: solutions.len(),
+            solutions,
+        }
+    }
+}
+
+pub fn verdict_symbol(verdict: Verdict) -> StyledObject<&'static str> {
+    match verdict {
+        Verdict::Passed => style("✔").green(),
+        Verdict::WrongAnswer => style("✕").red(),
+        Verdict::TimedOut => style("⧗").yellow(),
+        Verdict::RuntimeError => style("!").red().bold(),
+    }
+}
+
+/// Print a table of solutions and their results
+pub fn print_matrix(entries: &[BatchEntry]) {
+    println!(
+        "{}",
+        style(format!(
+            "{:<10} {:<10} {:<8} {:<8} {}",
+            "Problem", "Division", "Language", "Result", "Cases"
+        ))
+        .bold()
+        .underlined()
+    );
+    for entry in entries {
+        let result = match entry.status {
+            BatchStatus::Error => style("error".to_string()).red().bold(),
+            status => style(format!("{}/{}", entry.passed, entry.total)).fg(
+                if status == BatchStatus::Passed {
+                    console::Color::Green
+                } else {
+                    console::Color::Red
+                },
+            ),
+        };
+        let cases = match &entry.error {
+            Some(error) => style(error.clone()).dim().to_string(),
+            None => entry
+                .cases
+                .iter()
+                .map(|c| verdict_symbol(c.verdict).to_string())
+                .collect(),
+        };
+        let name = match &entry.variant {
+            Some(variant) => format!("{}.{}", entry.problem_id, variant),
+            None => entry.problem_id.to_string(),
+        };
+        println!(
+            "{:<10} {:<10} {:<8} {:<8} {}",
+            style(name).cyan(),
+            entry.division.name(),
+            match entry.language {
+                Language::CPP => "C++",
+                Language::Python => "Python",
+            },
+            result,
+            cases
+        );
+    }
+
+    let report = BatchReport::new(entries);
+    let summary = format!("{}/{} solutions passed", report.passed, report.total);
+    if report.passed == report.total {
+        println!("{}", style(summary).green().bold());
+    } else {
+        println!("{}", style(summary).red().bold());
+    }
+}
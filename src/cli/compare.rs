@@ -0,0 +1,183 @@
+use super::{
+    batch::{verdict_symbol, SolutionFile},
+    test_runner::{CaseResult, Verdict},
+};
+use crate::preferences::Language;
+use console::style;
+use indexmap::IndexSet;
+use serde::Serialize;
+
+/// Results of one variant of a solution
+#[derive(Debug, Serialize)]
+pub struct VariantResult {
+    /// `main` for `<id>.<ext>`
+    pub variant: String,
+    pub language: Language,
+    /// file name, like `1.py`. tells apart variants that only differ in language
+    pub file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub passed: usize,
+    pub total: usize,
+    pub cases: Vec<CaseResult>,
+}
+
+impl VariantResult {
+    pub fn new(solution: &SolutionFile, result: Result<Vec<CaseResult>, String>) -> Self {
+        let (cases, error) = match result {
+            Ok(cases) => (cases, None),
+            Err(error) => (vec![], Some(error)),
+        };
+        Self {
+            variant: solution.variant.clone().unwrap_or_else(|| "main".into()),
+            language: solution.lang,
+            file: solution
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            error,
+            passed: cases
+                .iter()
+                .filter(|c| c.verdict == Verdict::Passed)
+                .count(),
+            total: cases.len(),
+            cases,
+        }
+    }
+
+    fn case(&self, case: usize) -> Option<&CaseResult> {
+        self.cases.iter().find(|c| c.case == case)
+    }
+
+    /// Output for a 1-based case, if the variant finished it normally
+    pub fn answer(&self, case: usize) -> Option<&str> {
+        self.case(case)
+            .filter(|c| matches!(c.verdict, Verdict::Passed | Verdict::WrongAnswer))
+            .map(|c| c.output.trim())
+    }
+}
+
+/// Every variant of a solution run on the same cases, as printed by `--format json`
+#[derive(Debug, Serialize)]
+pub struct Comparison<'a> {
+    pub problem_id: u64,
+    pub variants: &'a [VariantResult],
+    /// 1-based numbers of cases where variants gave different answers
+    pub disagreements: Vec<usize>,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(problem_id: u64, variants: &'a [VariantResult]) -> Self {
+        // timeouts and crashes don't count as an answer
+        let disagreements = case_numbers(variants)
+            .into_iter()
+            .filter(|&case| {
+                variants
+                    .iter()
+                    .filter_map(|v| v.answer(case))
+                    .collect::<IndexSet<_>>()
+                    .len()
+                    > 1
+            })
+            .collect();
+        Self {
+            problem_id,
+            variants,
+            disagreements,
+        }
+    }
+}
+
+/// every case that was run by some variant, in order
+fn case_numbers(variants: &[VariantResult]) -> Vec<usize> {
+    let mut cases = variants
+        .iter()
+        .flat_map(|v| v.cases.iter().map(|c| c.case))
+        .collect::<IndexSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    cases.sort_unstable();
+    cases
+}
+
+/// Print a table of cases and the verdict of each variant
+pub fn print_comparison(comparison: &Comparison) {
+    for variant in comparison.variants {
+        if let Some(error) = &variant.error {
+            println!(
+                "{} {}",
+                style(format!("{}:", variant.file)).red().bold(),
+                style(error).dim()
+            );
+        }
+    }
+    let tested = comparison
+        .variants
+        .iter()
+        .filter(|v| v.error.is_none())
+        .collect::<Vec<_>>();
+    if tested.is_empty() {
+        return;
+    }
+
+    let width = tested
+        .iter()
+        .map(|v| v.file.len())
+        .max()
+        .unwrap_or(0)
+        .max(10);
+    let mut header = format!("{:<6}", "Case");
+    for variant in &tested {
+        header += &format!(" {:<width$}", variant.file);
+    }
+    println!("{}", style(header).bold().underlined());
+    for case in case_numbers(comparison.variants) {
+        let mut row = format!("{:<6}", case);
+        for variant in &tested {
+            let symbol = variant
+                .case(case)
+                .map(|c| verdict_symbol(c.verdict).to_string())
+                .unwrap_or_else(|| " ".into());
+            // symbols are one column wide
+            row += &format!(" {}{:<2$}", symbol, "", width - 1);
+        }
+        if comparison.disagreements.contains(&case) {
+            row += &style("disagree").red().bold().to_string();
+        }
+        println!("{}", row);
+    }
+    let mut totals = format!("{:<6}", "Passed");
+    for variant in &tested {
+        totals += &format!(
+            " {:<width$}",
+            format!("{}/{}", variant.passed, variant.total)
+        );
+    }
+    println!("{}", style(totals).dim());
+
+    if comparison.disagreements.is_empty() {
+        println!("{}", style("All variants agree").green().bold());
+    } else {
+        println!(
+            "{}",
+            style(format!(
+                "Variants disagree on case{} {}",
+                if comparison.disagreements.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+                comparison
+                    .disagreements
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+            .red()
+            .bold()
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use clap::ValueEnum;
+use console::{pad_str, style, truncate_str, Alignment, Style, Term};
+use similar::{ChangeTag, DiffOp, DiffTag, TextDiff};
+use std::fmt;
+
+/// Width used for side-by-side diffs when stderr isn't a terminal
+const DEFAULT_WIDTH: usize = 100;
+/// Room taken by the log level prefix
+const LOG_PREFIX_WIDTH: usize = 3;
+
+/// How to show the difference between expected and actual output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum DiffStyle {
+    /// Changed lines one after another, marked with - and +
+    #[default]
+    Inline,
+    /// Expected and actual output in two columns
+    SideBySide,
+    /// Changed words marked inside each line, like `git diff --word-diff`
+    Words,
+}
+
+/// The first token where two outputs differ
+#[derive(Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// 1-based
+    pub line: usize,
+    /// 1-based
+    pub token: usize,
+    /// the token, or `end of line`/`end of output` if there is none
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "first difference at line {}, token {}: expected {}, got {}",
+            self.line, self.token, self.expected, self.actual
+        )
+    }
+}
+
+/// Find the first differing whitespace-separated token, line by line
+/// Returns None if the outputs only differ in whitespace
+pub fn first_difference(expected: &str, actual: &str) -> Option<Mismatch> {
+    let mut expected_lines = expected.trim().lines();
+    let mut actual_lines = actual.trim().lines();
+    let mut line = 0;
+    loop {
+        line += 1;
+        let (e, a) = (expected_lines.next(), actual_lines.next());
+        if e.is_none() && a.is_none() {
+            return None;
+        }
+        let mut expected_tokens = e.unwrap_or("").split_whitespace();
+        let mut actual_tokens = a.unwrap_or("").split_whitespace();
+        let mut token = 0;
+        loop {
+            token += 1;
+            let (et, at) = (expected_tokens.next(), actual_tokens.next());
+            if et.is_none() && at.is_none() {
+                break;
+            }
+            if et != at {
+                let describe = |line: Option<&str>, token: Option<&str>| match (line, token) {
+                    (_, Some(token)) => token.to_string(),
+                    (Some(_), None) => "end of line".into(),
+                    (None, None) => "end of output".into(),
+                };
+                return Some(Mismatch {
+                    line,
+                    token,
+                    expected: describe(e, et),
+                    actual: describe(a, at),
+                });
+            }
+        }
+    }
+}
+
+/// Render a diff of the lines around the first mismatch, with `context` lines on each side
+pub fn render_diff(
+    expected: &str,
+    actual: &str,
+    diff_style: DiffStyle,
+    context: usize,
+) -> Vec<String> {
+    let diff = TextDiff::from_lines(expected, actual);
+    let groups = diff.grouped_ops(context);
+    let Some(first) = groups.first() else {
+        return vec![];
+    };
+
+    let mut lines = match diff_style {
+        DiffStyle::Inline => render_inline(&diff, first),
+        DiffStyle::SideBySide => render_side_by_side(&diff, first),
+        DiffStyle::Words => render_words(&diff, first),
+    };
+    if groups.len() > 1 {
+        let changed: usize = groups[1..]
+            .iter()
+            .flatten()
+            .filter(|op| op.tag() != DiffTag::Equal)
+            .map(|op| op.old_range().len().max(op.new_range().len()))
+            .sum();
+        lines.push(
+            style(format!("… {} more differing lines", changed))
+                .dim()
+                .to_string(),
+        );
+    }
+    lines
+}
+
+/// line number column, using the actual output's numbering
+fn gutter(line: Option<usize>) -> String {
+    style(
+        line.map(|l| format!("{:<4}", l + 1))
+            .unwrap_or_else(|| "    ".into()),
+    )
+    .dim()
+    .to_string()
+}
+
+fn render_inline(diff: &TextDiff<str>, ops: &[DiffOp]) -> Vec<String> {
+    ops.iter()
+        .flat_map(|op| diff.iter_changes(op))
+        .map(|change| {
+            let (sign, s) = match change.tag() {
+                ChangeTag::Delete => ("-", Style::new().red()),
+                ChangeTag::Insert => ("+", Style::new().green()),
+                ChangeTag::Equal => (" ", Style::new()),
+            };
+            format!(
+                "{}｜ {}{}",
+                gutter(change.new_index()),
+                s.apply_to(sign).bold(),
+                s.apply_to(change.value().trim_end())
+            )
+        })
+        .collect()
+}
+
+fn render_side_by_side(diff: &TextDiff<str>, ops: &[DiffOp]) -> Vec<String> {
+    let width = Term::stderr()
+        .size_checked()
+        .map(|(_, cols)| cols as usize)
+        .unwrap_or(DEFAULT_WIDTH);
+    // gutter and separator take 9 columns
+    let column = (width.saturating_sub(LOG_PREFIX_WIDTH + 9) / 2).max(10);
+    let cell = |text: &str, s: &Style| {
+        let text = truncate_str(text.trim_end(), column, "…");
+        s.apply_to(pad_str(&text, column, Alignment::Left, None))
+            .to_string()
+    };
+
+    let mut lines = vec![format!(
+        "    {} │ {}",
+        style(pad_str("Expected", column, Alignment::Left, None)).bold(),
+        style("Actual").bold()
+    )];
+    let old = diff.old_slices();
+    let new = diff.new_slices();
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let rows = old_range.len().max(new_range.len());
+        for i in 0..rows {
+            let left = old_range.clone().nth(i);
+            let right = new_range.clone().nth(i);
+            let s = if tag == DiffTag::Equal {
+                Style::new()
+            } else {
+                Style::new().bold()
+            };
+            let separator = if tag == DiffTag::Equal {
+                style("│").dim()
+            } else {
+                style("┃").yellow()
+            };
+            lines.push(format!(
+                "{}{} {} {}",
+                gutter(right),
+                cell(left.map(|l| old[l]).unwrap_or(""), &s.clone().red()),
+                separator,
+                s.green().apply_to(truncate_str(
+                    right.map(|l| new[l].trim_end()).unwrap_or(""),
+                    column,
+                    "…"
+                ))
+            ));
+        }
+    }
+    lines
+}
+
+fn render_words(diff: &TextDiff<str>, ops: &[DiffOp]) -> Vec<String> {
+    let old = diff.old_slices();
+    let new = diff.new_slices();
+    let mut lines = vec![];
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            lines
+                .extend(new_range.map(|l| format!("{}｜  {}", gutter(Some(l)), new[l].trim_end())));
+            continue;
+        }
+        let rows = old_range.len().max(new_range.len());
+        for i in 0..rows {
+            let left = old_range.clone().nth(i).map(|l| old[l].trim_end());
+            let right = new_range.clone().nth(i);
+            let text = match (left, right.map(|l| new[l].trim_end())) {
+                (Some(left), Some(right)) => word_diff(left, right),
+                (Some(left), None) => style(format!("[-{}-]", left)).red().to_string(),
+                (None, Some(right)) => style(format!("{{+{}+}}", right)).green().to_string(),
+                (None, None) => unreachable!(),
+            };
+            lines.push(format!(
+                "{}｜ {} {}",
+                gutter(right),
+                style("~").yellow().bold(),
+                text
+            ));
+        }
+    }
+    lines
+}
+
+/// mark removed words as [-old-] and added words as {+new+}
+fn word_diff(old: &str, new: &str) -> String {
+    let diff = TextDiff::from_words(old, new);
+    let mut out = String::new();
+    for change in diff.iter_all_changes() {
+        let value = change.value();
+        // only mark changed words, not the spaces between them
+        let whitespace = value.trim().is_empty();
+        match change.tag() {
+            ChangeTag::Equal => out.push_str(value),
+            ChangeTag::Delete if whitespace => {}
+            ChangeTag::Insert if whitespace => out.push_str(value),
+            ChangeTag::Delete => out += &style(format!("[-{}-]", value)).red().bold().to_string(),
+            ChangeTag::Insert => {
+                out += &style(format!("{{+{}+}}", value)).green().bold().to_string()
+            }
+        }
+    }
+    out
+}
@@ -1,26 +1,37 @@
 mod auth;
+mod batch;
+mod compare;
+mod diff;
+mod output;
+mod pager;
 mod preferences;
 mod problem;
+mod render;
+mod report;
 mod solution;
 mod status_spinner;
+mod test_runner;
 
 use crate::{
     credential_storage::{autoselect_cred_storage, CredentialStorageError},
-    http_client::{HttpClient, HttpClientError},
-    preferences::{DataStore, PreferencesError},
+    http_client::{HttpClient, HttpClientError, HttpClientOptions},
+    preferences::{DataStore, Preferences, PreferencesError},
 };
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 use console::style;
 use directories::ProjectDirs;
-use env_logger::Env;
-use indicatif::MultiProgress;
+use env_logger::{Env, WriteStyle};
+use indicatif::{MultiProgress, ProgressDrawTarget};
 use indicatif_log_bridge::LogWrapper;
 use log::{error, Level, LevelFilter};
+use output::{print_json, print_json_error, OutputFormat};
+use serde::Serialize;
 use status_spinner::StatusSpinner;
 use std::{
     io::{stdout, Write},
     process::ExitCode,
+    time::Duration,
 };
 use thiserror::Error;
 
@@ -37,6 +48,18 @@ struct Args {
     #[arg(short, long, value_enum)]
     log_level: Option<LevelFilter>,
 
+    /// Serve everything from the cache and never touch the network
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Print long output directly instead of through $PAGER
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// Output format. `json` prints a single JSON document on stdout
+    #[arg(long, value_enum, default_value_t, global = true)]
+    format: OutputFormat,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -81,10 +104,15 @@ pub enum CliError {
     IoError(#[from] std::io::Error),
     #[error("Input error: {0}")]
     InputError(#[from] dialoguer::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
 
     /// used when the message has already been printed and we just need to exit
     #[error("")]
     ExitError,
+    /// used when test results have already been printed and some didn't pass
+    #[error("Some tests failed")]
+    TestsFailed,
 }
 
 type Result<T = ()> = std::result::Result<T, CliError>;
@@ -134,6 +162,13 @@ fn setup_logging() -> (MultiProgress, Args) {
     }
 
     let multi = MultiProgress::new();
+    if args.format == OutputFormat::Json {
+        // keep stdout parseable: no spinners and no colors
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+        logger.write_style(WriteStyle::Never);
+        multi.set_draw_target(ProgressDrawTarget::hidden());
+    }
     let logger = logger.build();
     let log_filter = logger.filter();
     LogWrapper::new(multi.clone(), logger).try_init().unwrap();
@@ -142,16 +177,57 @@ fn setup_logging() -> (MultiProgress, Args) {
     (multi, args)
 }
 
+/// build HTTP client settings from preferences and environment variables
+fn client_options(prefs: &Preferences) -> HttpClientOptions {
+    let defaults = HttpClientOptions::default();
+    HttpClientOptions {
+        // env var takes priority over the preference
+        base_url: std::env::var("USACO_BASE_URL")
+            .ok()
+            .or_else(|| prefs.base_url.clone())
+            .unwrap_or(defaults.base_url),
+        timeout: prefs
+            .request_timeout
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.timeout),
+        max_retries: prefs.max_retries.unwrap_or(defaults.max_retries),
+        rate_limit: prefs.rate_limit.unwrap_or(defaults.rate_limit),
+        offline: prefs.offline,
+    }
+}
+
 async fn run_internal(multi: MultiProgress, args: Args) -> Result {
     let dirs = ProjectDirs::from("com", "grimsteel", "usaco-cli").unwrap();
     let prefs = DataStore::new(dirs.clone()).await?;
     let cred_storage = autoselect_cred_storage(&dirs).await;
-    let client = HttpClient::init(cred_storage.clone());
+    let mut client_options = client_options(&*prefs.read()?);
+    client_options.offline |= args.offline;
+    let client = HttpClient::init(cred_storage.clone(), client_options.clone())
+        .with_password_prompt(auth::password_prompt(multi.clone()));
 
+    let format = args.format;
     match args.command {
         Command::Ping => {
             let status = StatusSpinner::new("Loading...", &multi);
-            if let Some(ping) = client.ping().await? {
+            if client_options.offline {
+                status.finish("Offline mode is enabled", false);
+                return Err(match format {
+                    OutputFormat::Json => HttpClientError::Offline.into(),
+                    OutputFormat::Text => CliError::ExitError,
+                });
+            }
+            let ping = client.ping().await?;
+            if format == OutputFormat::Json {
+                #[derive(Serialize)]
+                struct PingOutput {
+                    online: bool,
+                    ping_ms: Option<u128>,
+                }
+                print_json(&PingOutput {
+                    online: ping.is_some(),
+                    ping_ms: ping,
+                })?;
+            } else if let Some(ping) = ping {
                 status.finish("USACO servers are online", true);
                 // print the ping
                 println!(
@@ -169,21 +245,35 @@ async fn run_internal(multi: MultiProgress, args: Args) -> Result {
             let name = command.get_name().to_string();
             generate(shell, &mut command, name, &mut stdout());
         }
-        Command::Auth { command } => auth::handle(command, client, cred_storage, multi).await?,
-        Command::Problem { command } => problem::handle(command, client, &prefs, multi).await?,
+        Command::Auth { command } => {
+            auth::handle(command, client, cred_storage, multi, format).await?
+        }
+        Command::Problem { command } => {
+            problem::handle(command, client, &prefs, multi, !args.no_pager, format).await?
+        }
         Command::Solution { command } => {
-            solution::handle(command, client, &prefs, multi, dirs).await?
+            solution::handle(command, client, &prefs, multi, dirs, !args.no_pager, format).await?
+        }
+        Command::Preferences { command } => {
+            preferences::handle(command, &prefs, multi, format).await?
         }
-        Command::Preferences { command } => preferences::handle(command, &prefs, multi).await?,
     }
 
+    // persist the LRU order
+    prefs.flush_cache().await?;
+
     Ok(())
 }
 
 pub async fn run() -> ExitCode {
     let (multi, args) = setup_logging();
+    let format = args.format;
     if let Err(err) = run_internal(multi, args).await {
-        if !matches!(err, CliError::ExitError) {
+        if matches!(err, CliError::TestsFailed) {
+            // the results are the output
+        } else if format == OutputFormat::Json {
+            print_json_error(&err, status_spinner::take_last_failure());
+        } else if !matches!(err, CliError::ExitError) {
             error!("Unexpected error: {}", err);
         }
         return ExitCode::from(1);
@@ -191,3 +281,46 @@ pub async fn run() -> ExitCode {
 
     ExitCode::SUCCESS
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use clap::{CommandFactory, Parser};
+
+    #[test]
+    fn args_are_valid() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn subcommand_options_dont_clash_with_global_ones() {
+        // global options share a namespace with every subcommand's options
+        for args in [
+            &["usaco", "problem", "export", "1"][..],
+            &["usaco", "problem", "export", "1", "--to", "html"],
+            &[
+                "usaco",
+                "problem",
+                "info",
+                "1",
+                "--format",
+                "json",
+                "--offline",
+                "--no-pager",
+            ],
+            &[
+                "usaco",
+                "solution",
+                "test",
+                "1",
+                "--report",
+                "tap",
+                "--report-path",
+                "report.tap",
+            ],
+        ] {
+            let parsed = Args::try_parse_from(args);
+            assert!(parsed.is_ok(), "{:?}: {}", args, parsed.unwrap_err());
+        }
+    }
+}
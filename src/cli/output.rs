@@ -0,0 +1,80 @@
+use super::CliError;
+use crate::http_client::HttpClientError;
+use clap::ValueEnum;
+use console::strip_ansi_codes;
+use serde::Serialize;
+use std::io::{stdout, Write};
+
+/// How command results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Styled text for humans
+    #[default]
+    Text,
+    /// A single JSON document on stdout, for scripts
+    Json,
+}
+
+#[derive(Serialize)]
+struct ErrorOutput<'a> {
+    error: ErrorBody<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'static str,
+    message: &'a str,
+}
+
+/// Print a value as a JSON document on stdout
+pub fn print_json<T: Serialize>(value: &T) -> super::Result {
+    let mut stdout = stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Print an error as a JSON error object
+/// `message` is used for errors that were already reported, like `ExitError`
+pub fn print_json_error(err: &CliError, message: Option<String>) {
+    let message = match err {
+        CliError::ExitError => message.unwrap_or_else(|| "Command failed".into()),
+        err => err.to_string(),
+    };
+    let output = ErrorOutput {
+        error: ErrorBody {
+            code: error_code(err),
+            message: &strip_ansi_codes(&message),
+        },
+    };
+    // serializing plain strings can't fail, and there's nowhere to report a closed stdout
+    let _ = writeln!(
+        stdout().lock(),
+        "{}",
+        serde_json::to_string_pretty(&output).unwrap()
+    );
+}
+
+/// Stable machine-readable code for an error
+fn error_code(err: &CliError) -> &'static str {
+    match err {
+        CliError::PreferencesError(_) => "preferences",
+        CliError::ApiError(err) => match err {
+            HttpClientError::Http(_) => "network",
+            HttpClientError::LoggedOut => "logged_out",
+            HttpClientError::SessionExpired => "session_expired",
+            HttpClientError::PasswordRequired => "password_required",
+            HttpClientError::InvalidUsernamePassword => "invalid_credentials",
+            HttpClientError::ProblemNotFound => "problem_not_found",
+            HttpClientError::ContestNotFound => "contest_not_found",
+            HttpClientError::Offline => "offline",
+            _ => "api",
+        },
+        CliError::CredentialStorageError(_) => "credential_storage",
+        CliError::IoError(_) => "io",
+        CliError::InputError(_) => "input",
+        CliError::JsonError(_) => "json",
+        CliError::ExitError => "failed",
+        CliError::TestsFailed => "tests_failed",
+    }
+}
@@ -0,0 +1,79 @@
+use console::{measure_text_width, Term};
+use log::warn;
+use std::{env, io::ErrorKind, process::Stdio};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Used when $PAGER isn't set. -R passes colors through
+const DEFAULT_PAGER: &str = "less -R";
+/// Exit codes from `sh` (not found, not executable) and `cmd` (not recognized)
+/// when the pager can't be run
+const NOT_FOUND_EXIT_CODES: [i32; 3] = [126, 127, 9009];
+
+/// Width to wrap text to, or None if stdout isn't a terminal
+pub fn terminal_width() -> Option<usize> {
+    Term::stdout().size_checked().map(|(_, cols)| cols as usize)
+}
+
+/// Print output, going through a pager if it doesn't fit on the screen, like `git log`
+pub async fn page(output: &str, enabled: bool) -> std::io::Result<()> {
+    let fits = match Term::stdout().size_checked() {
+        Some((rows, cols)) => {
+            // count lines the terminal will wrap
+            let lines: usize = output
+                .lines()
+                .map(|line| measure_text_width(line).div_ceil(cols as usize).max(1))
+                .sum();
+            lines < rows as usize
+        }
+        // not a terminal
+        None => true,
+    };
+    let pager = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.into());
+    if !enabled || fits || pager.trim().is_empty() || pager == "cat" {
+        print!("{}", output);
+        return Ok(());
+    }
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd.exe");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    // same defaults as git: quit if it fits, keep colors, don't clear the screen
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.arg(&pager).stdin(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Could not start pager \"{}\": {}", pager, e);
+            print!("{}", output);
+            return Ok(());
+        }
+    };
+
+    let mut stdin = child.stdin.take().unwrap();
+    match stdin.write_all(output.as_bytes()).await {
+        // the pager was quit before reading everything
+        Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e),
+        _ => {}
+    }
+    drop(stdin);
+    let status = child.wait().await?;
+    // the shell started but couldn't run the pager, so nothing was shown
+    if status
+        .code()
+        .is_some_and(|code| NOT_FOUND_EXIT_CODES.contains(&code))
+    {
+        warn!(
+            "Could not start pager \"{}\". Set $PAGER to change it",
+            pager
+        );
+        print!("{}", output);
+    }
+    Ok(())
+}
@@ -1,11 +1,17 @@
 use crate::{
-    cli::status_spinner::StatusSpinner,
-    preferences::{CPPCompiler, DataStore, Language},
+    cli::{
+        output::{print_json, OutputFormat},
+        status_spinner::StatusSpinner,
+    },
+    http_client::{HttpClientOptions, DEFAULT_BASE_URL},
+    preferences::{CPPCompiler, DataStore, Language, Preferences, DEFAULT_CACHE_SIZE},
 };
 use clap::{Subcommand, ValueEnum};
 use console::{strip_ansi_codes, style, user_attended};
-use dialoguer::{theme::ColorfulTheme, Input, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
 use indicatif::MultiProgress;
+use serde::Serialize;
+use serde_json::{json, Value};
 use std::{borrow::Cow, env::current_dir, path::PathBuf};
 use tokio::fs::canonicalize;
 
@@ -44,6 +50,21 @@ pub enum SetValues {
         #[arg(value_enum)]
         value: Option<PathBuf>,
     },
+    /// USACO site URL, for mirrors and local mock servers
+    BaseUrl { value: Option<String> },
+    /// Timeout in seconds for connecting and for each read
+    RequestTimeout { value: Option<u64> },
+    /// How many times to retry failed requests
+    MaxRetries { value: Option<u32> },
+    /// Maximum requests per second (0 for unlimited)
+    RateLimit { value: Option<u32> },
+    /// Serve everything from the cache and never touch the network
+    Offline {
+        #[arg(action = clap::ArgAction::Set)]
+        value: Option<bool>,
+    },
+    /// Maximum number of cached problems (0 for unlimited)
+    CacheSize { value: Option<usize> },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -56,14 +77,102 @@ pub enum PrefKey {
     CPPCompiler,
     /// Directory to hold solutions in
     SolutionsDirectory,
+    /// USACO site URL, for mirrors and local mock servers
+    BaseUrl,
+    /// Timeout in seconds for connecting and for each read
+    RequestTimeout,
+    /// How many times to retry failed requests
+    MaxRetries,
+    /// Maximum requests per second (0 for unlimited)
+    RateLimit,
+    /// Serve everything from the cache and never touch the network
+    Offline,
+    /// Maximum number of cached problems (0 for unlimited)
+    CacheSize,
+}
+
+/// format a numeric network preference, showing the default if unset
+fn format_number<T: ToString>(value: Option<T>, default: T, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{}{}", value.to_string(), unit),
+        None => format!("{}{} (default)", default.to_string(), unit),
+    }
+}
+
+fn format_cache_size(value: Option<usize>) -> String {
+    match value {
+        Some(0) => "Unlimited".into(),
+        value => format_number(value, DEFAULT_CACHE_SIZE, " problems"),
+    }
+}
+
+/// Preferences with defaults filled in, for `--format json`
+#[derive(Serialize)]
+struct EffectivePreferences<'a> {
+    current_problem: Option<u64>,
+    preferred_language: Language,
+    cpp_compiler: CPPCompiler,
+    solutions_directory: Option<&'a PathBuf>,
+    base_url: &'a str,
+    /// in seconds
+    request_timeout: u64,
+    max_retries: u32,
+    rate_limit: u32,
+    offline: bool,
+    cache_size: usize,
+}
+
+impl<'a> EffectivePreferences<'a> {
+    fn new(prefs: &'a Preferences, defaults: &HttpClientOptions) -> Self {
+        Self {
+            current_problem: prefs.current_problem,
+            preferred_language: prefs.preferred_language,
+            cpp_compiler: prefs.cpp_compiler,
+            solutions_directory: prefs.solutions_dir.as_ref(),
+            base_url: prefs.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL),
+            request_timeout: prefs.request_timeout.unwrap_or(defaults.timeout.as_secs()),
+            max_retries: prefs.max_retries.unwrap_or(defaults.max_retries),
+            rate_limit: prefs.rate_limit.unwrap_or(defaults.rate_limit),
+            offline: prefs.offline,
+            cache_size: prefs.cache_size.unwrap_or(DEFAULT_CACHE_SIZE),
+        }
+    }
+}
+
+/// prompt for a number, defaulting to the current value
+fn prompt_number<T>(prompt: &str, current: T) -> dialoguer::Result<T>
+where
+    T: Clone + ToString + std::str::FromStr,
+    T::Err: ToString,
+{
+    Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(current)
+        .interact_text()
 }
 
 pub async fn handle(
     command: Option<Command>,
     prefs: &DataStore,
     multi: MultiProgress,
+    format: OutputFormat,
 ) -> super::Result {
+    let defaults = HttpClientOptions::default();
     match command {
+        Some(Command::Get { key }) if format == OutputFormat::Json => {
+            let lock = prefs.read()?;
+            let key = key
+                .to_possible_value()
+                .unwrap()
+                .get_name()
+                .replace('-', "_");
+            let mut values = serde_json::to_value(EffectivePreferences::new(&lock, &defaults))?;
+            let value = values.get_mut(&key).map(Value::take).unwrap_or_default();
+            print_json(&json!({ "key": key, "value": value }))?;
+        }
+        None if format == OutputFormat::Json => {
+            print_json(&EffectivePreferences::new(&*prefs.read()?, &defaults))?;
+        }
         Some(Command::Get { key }) => {
             let lock = prefs.read()?;
             let value = match key {
@@ -87,6 +196,38 @@ pub async fn handle(
                 }
                 .bright()
                 .bold(),
+                PrefKey::BaseUrl => style(Cow::Owned(
+                    lock.base_url
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_BASE_URL.into()),
+                ))
+                .blue(),
+                PrefKey::RequestTimeout => style(Cow::Owned(format_number(
+                    lock.request_timeout,
+                    defaults.timeout.as_secs(),
+                    "s",
+                )))
+                .cyan(),
+                PrefKey::MaxRetries => style(Cow::Owned(format_number(
+                    lock.max_retries,
+                    defaults.max_retries,
+                    "",
+                )))
+                .cyan(),
+                PrefKey::RateLimit => style(Cow::Owned(format_number(
+                    lock.rate_limit,
+                    defaults.rate_limit,
+                    "/s",
+                )))
+                .cyan(),
+                PrefKey::CacheSize => style(Cow::Owned(format_cache_size(lock.cache_size))).cyan(),
+                PrefKey::Offline => {
+                    if lock.offline {
+                        style(Cow::Borrowed("Enabled")).yellow()
+                    } else {
+                        style(Cow::Borrowed("Disabled")).green()
+                    }
+                }
             }
             .bright()
             .bold()
@@ -99,6 +240,12 @@ pub async fn handle(
                         PrefKey::PreferredLanguage => "Preferred language:",
                         PrefKey::CPPCompiler => "C++ compiler:",
                         PrefKey::SolutionsDirectory => "Solutions directory:",
+                        PrefKey::BaseUrl => "Base URL:",
+                        PrefKey::RequestTimeout => "Request timeout:",
+                        PrefKey::MaxRetries => "Max retries:",
+                        PrefKey::RateLimit => "Rate limit:",
+                        PrefKey::Offline => "Offline mode:",
+                        PrefKey::CacheSize => "Cache size:",
                     })
                     .dim(),
                     value
@@ -188,11 +335,87 @@ pub async fn handle(
 
                         lock.solutions_dir = Some(input);
                     }
+                    SetValues::BaseUrl { value } => {
+                        let input = if let Some(value) = value {
+                            value
+                        } else {
+                            Input::with_theme(&ColorfulTheme::default())
+                                .with_prompt("Enter a base URL")
+                                .default(
+                                    lock.base_url
+                                        .clone()
+                                        .unwrap_or_else(|| DEFAULT_BASE_URL.into()),
+                                )
+                                .interact_text()?
+                        };
+                        let input = input.trim_end_matches('/');
+
+                        // don't store the default
+                        lock.base_url = if input == DEFAULT_BASE_URL {
+                            None
+                        } else {
+                            Some(input.into())
+                        };
+                    }
+                    SetValues::RequestTimeout { value } => {
+                        let input = match value {
+                            Some(value) => value,
+                            None => prompt_number(
+                                "Enter a timeout in seconds",
+                                lock.request_timeout.unwrap_or(defaults.timeout.as_secs()),
+                            )?,
+                        };
+                        lock.request_timeout = Some(input);
+                    }
+                    SetValues::MaxRetries { value } => {
+                        let input = match value {
+                            Some(value) => value,
+                            None => prompt_number(
+                                "Enter the maximum number of retries",
+                                lock.max_retries.unwrap_or(defaults.max_retries),
+                            )?,
+                        };
+                        lock.max_retries = Some(input);
+                    }
+                    SetValues::RateLimit { value } => {
+                        let input = match value {
+                            Some(value) => value,
+                            None => prompt_number(
+                                "Enter the maximum requests per second",
+                                lock.rate_limit.unwrap_or(defaults.rate_limit),
+                            )?,
+                        };
+                        lock.rate_limit = Some(input);
+                    }
+                    SetValues::Offline { value } => {
+                        let input = if let Some(value) = value {
+                            value
+                        } else {
+                            Confirm::with_theme(&ColorfulTheme::default())
+                                .with_prompt("Enable offline mode?")
+                                .default(lock.offline)
+                                .interact()?
+                        };
+                        lock.offline = input;
+                    }
+                    SetValues::CacheSize { value } => {
+                        let input = match value {
+                            Some(value) => value,
+                            None => prompt_number(
+                                "Enter the maximum number of cached problems (0 for unlimited)",
+                                lock.cache_size.unwrap_or(DEFAULT_CACHE_SIZE),
+                            )?,
+                        };
+                        lock.cache_size = Some(input);
+                    }
                 }
             }
             let status = StatusSpinner::new("Saving...", &multi);
             prefs.save_prefs().await?;
             status.finish("Saved", true);
+            if format == OutputFormat::Json {
+                print_json(&EffectivePreferences::new(&*prefs.read()?, &defaults))?;
+            }
         }
         None => {
             // list all values
@@ -241,6 +464,61 @@ pub async fn handle(
                     style("Not set").red().bright().bold().to_string()
                 }
             );
+            println!(
+                "{} {}",
+                style("Base URL:").dim(),
+                style(lock.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))
+                    .bright()
+                    .blue()
+                    .bold(),
+            );
+            println!(
+                "{} {}",
+                style("Request timeout:").dim(),
+                style(format_number(
+                    lock.request_timeout,
+                    defaults.timeout.as_secs(),
+                    "s"
+                ))
+                .bright()
+                .cyan()
+                .bold(),
+            );
+            println!(
+                "{} {}",
+                style("Max retries:").dim(),
+                style(format_number(lock.max_retries, defaults.max_retries, ""))
+                    .bright()
+                    .cyan()
+                    .bold(),
+            );
+            println!(
+                "{} {}",
+                style("Rate limit:").dim(),
+                style(format_number(lock.rate_limit, defaults.rate_limit, "/s"))
+                    .bright()
+                    .cyan()
+                    .bold(),
+            );
+            println!(
+                "{} {}",
+                style("Cache size:").dim(),
+                style(format_cache_size(lock.cache_size))
+                    .bright()
+                    .cyan()
+                    .bold(),
+            );
+            println!(
+                "{} {}",
+                style("Offline mode:").dim(),
+                if lock.offline {
+                    style("Enabled").yellow()
+                } else {
+                    style("Disabled").green()
+                }
+                .bright()
+                .bold(),
+            );
         }
     }
     Ok(())
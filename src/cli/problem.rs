@@ -1,14 +1,34 @@
-use super::{status_spinner::StatusSpinner, CliError};
+use super::{
+    output::{print_json, OutputFormat},
+    pager::{page, terminal_width},
+    render::{render_document, ExportFormat},
+    status_spinner::StatusSpinner,
+    CliError,
+};
 use crate::{
-    http_client::{HttpClient, HttpClientError, Problem},
-    preferences::DataStore,
+    http_client::{Division, HttpClient, HttpClientError, IoMode, Problem, RawProblemHtml},
+    preferences::{unix_now, DataStore, DEFAULT_CACHE_SIZE},
 };
-use clap::Subcommand;
+use bytes::Bytes;
+use clap::{builder::PossibleValuesParser, Subcommand};
 use console::{style, Color};
 use dialoguer::{theme::ColorfulTheme, Input};
-use indicatif::MultiProgress;
-use std::{future::Future, io::{stdin, Read}, process::Stdio};
-use tokio::process::Command as ProcessCommand;
+use futures_util::{stream, StreamExt};
+use indexmap::IndexSet;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use log::{debug, warn};
+use serde::Serialize;
+use std::{
+    future::Future,
+    io::{stdin, Read},
+    path::PathBuf,
+    process::Stdio,
+    time::Duration,
+};
+use tokio::{
+    fs::{create_dir_all, write},
+    process::Command as ProcessCommand,
+};
 
 #[derive(Subcommand, Debug)]
 pub enum Command {
@@ -16,6 +36,9 @@ pub enum Command {
     Info {
         /// Problem ID. Will prompt if not given and if current problem is not set
         id: Option<u64>,
+        /// Refetch the problem even if it is cached
+        #[arg(short, long)]
+        refresh: bool,
     },
     /// Open a problem in your default web browser
     Open {
@@ -25,6 +48,46 @@ pub enum Command {
         #[arg(short, long)]
         no_launch_browser: bool,
     },
+    /// Write a problem's sample cases to N.in and N.out files
+    Samples {
+        /// Problem ID. Will prompt if not given and if current problem is not set
+        id: Option<u64>,
+        /// Directory to write to
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Export a problem statement to Markdown, HTML, or plain text
+    Export {
+        /// Problem ID. Will prompt if not given and if current problem is not set
+        id: Option<u64>,
+        /// Format to export to
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Md)]
+        to: ExportFormat,
+        /// File to write to. Prints to standard output if not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Download problems into the cache ahead of time, e.g. for offline use
+    Prefetch {
+        /// Problem IDs to prefetch
+        #[arg(required_unless_present = "contest")]
+        ids: Vec<u64>,
+        /// Prefetch every problem in a contest, like "2023 December Contest" or "dec23"
+        #[arg(short, long)]
+        contest: Option<String>,
+        /// Only prefetch problems from this division
+        #[arg(short, long, value_parser = PossibleValuesParser::new(Division::get_all()))]
+        division: Option<String>,
+        /// Also download official test data, if it has been released
+        #[arg(short, long)]
+        test_data: bool,
+        /// Pin prefetched problems so they are never evicted from the cache
+        #[arg(short, long)]
+        pin: bool,
+        /// Refetch problems even if they are already cached
+        #[arg(short, long)]
+        refresh: bool,
+    },
     /// Manage the LRU problem info cache
     Cache {
         #[command(subcommand)]
@@ -42,6 +105,18 @@ pub enum CacheCommand {
         #[arg(short, long, num_args = 0..)]
         problem_ids: Vec<u64>,
     },
+    /// Pin problems so they are never evicted from the cache
+    Pin {
+        /// Problem IDs to pin
+        #[arg(required = true)]
+        problem_ids: Vec<u64>,
+    },
+    /// Unpin problems so they can be evicted again
+    Unpin {
+        /// Problem IDs to unpin
+        #[arg(required = true)]
+        problem_ids: Vec<u64>,
+    },
     /// Manually import a problem into the cache
     /// For environments where fetching the problem automatically does not work
     /// Problem content is read from standard input
@@ -52,12 +127,28 @@ pub enum CacheCommand {
     }
 }
 
-fn print_problem(problem: &Problem) {
+/// format how long ago a unix timestamp was
+fn format_age(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "unknown age".into();
+    }
+    let secs = unix_now().saturating_sub(timestamp);
+    match secs {
+        0..60 => "just now".into(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Print the problem statement, paging it if it's long
+async fn print_problem(problem: &Problem, pager: bool) -> super::Result {
+    let mut output = String::new();
     // problem name
-    println!("\n{}", style(&problem.name).bold().bright().underlined());
+    output += &format!("\n{}\n", style(&problem.name).bold().bright().underlined());
     // contest/division/number
-    println!(
-        "{}\n",
+    output += &format!(
+        "{}\n\n",
         style(format!(
             "{} {}{}",
             style(&problem.contest).yellow(),
@@ -68,7 +159,41 @@ fn print_problem(problem: &Problem) {
         ))
         .dim()
     );
-    println!("{}", problem.description);
+    // I/O and limits
+    let label = |s: &str| style(format!("{:<14}", s)).bold().cyan().to_string();
+    let io_mode = |mode: &IoMode, stdio: &str| match mode {
+        IoMode::Stdio => stdio.to_string(),
+        IoMode::File(name) => format!("file {}", style(name).yellow()),
+    };
+    output += &format!(
+        "{}{}\n",
+        label("Input:"),
+        io_mode(&problem.input, "standard input")
+    );
+    output += &format!(
+        "{}{}\n",
+        label("Output:"),
+        io_mode(&problem.output, "standard output")
+    );
+    output += &format!(
+        "{}{}s {}\n",
+        label("Time limit:"),
+        problem.time_limit().as_secs_f64(),
+        style(format!(
+            "({}s for Python)",
+            problem.time_limit().as_secs_f64() * 2.0
+        ))
+        .dim()
+    );
+    output += &format!(
+        "{}{} MB\n\n",
+        label("Memory limit:"),
+        problem.memory_limit_mb()
+    );
+    output += &render_document(&problem.description, terminal_width());
+    output.push('\n');
+    page(&output, pager).await?;
+    Ok(())
 }
 
 /// Import a problem into the store by parsing problem HTML from stdin
@@ -82,7 +207,7 @@ async fn import_problem_stdin(problem_id: u64, client: &HttpClient, store: &Data
     // parse string
     let input = String::from_utf8_lossy(&input).into_owned();
 
-    let problem = client.parse_problem_html(problem_id, input, false).await?;
+    let (problem, raw) = client.parse_problem_html(problem_id, input, false).await?;
 
     let message = format!(
         "Successfully imported {} ({})",
@@ -94,16 +219,75 @@ async fn import_problem_stdin(problem_id: u64, client: &HttpClient, store: &Data
     );
 
     // store
-    store.insert_cache(problem).await?;
+    store.insert_cache(problem, &raw, false).await?;
 
     Ok(message)
 } 
 
+/// Number of problems to fetch at once when prefetching
+const PREFETCH_CONCURRENCY: usize = 4;
+
+/// A problem downloaded by prefetch
+/// `raw` is None if the problem was already cached
+struct Prefetched {
+    problem: Problem,
+    raw: Option<RawProblemHtml>,
+    test_data: Option<Bytes>,
+}
+
+/// Fetch a problem and optionally its official test data, skipping whatever is already cached
+async fn prefetch_problem(
+    id: u64,
+    client: &HttpClient,
+    store: &DataStore,
+    refresh: bool,
+    test_data: bool,
+) -> super::Result<Prefetched> {
+    let cached = if refresh {
+        None
+    } else {
+        store
+            .get_cache(id)?
+            .filter(|p| !p.needs_refresh())
+            .map(|p| p.problem.clone())
+    };
+    let (problem, raw) = match cached {
+        Some(problem) => (problem, None),
+        None => {
+            let (problem, raw) = client.get_problem(id).await?;
+            (problem, Some(raw))
+        }
+    };
+
+    let test_data = match &problem.released_data {
+        Some(rd) if test_data && (refresh || !store.has_test_data(id).await?) => Some(
+            client
+                .download_official_test_data(&rd.official_test_case_url)
+                .await?,
+        ),
+        _ => None,
+    };
+
+    Ok(Prefetched {
+        problem,
+        raw,
+        test_data,
+    })
+}
+
+/// How long to wait when refetching a stale cached problem before using the cached copy
+const STALE_REFRESH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Load a problem from the cache, or fetch it if it isn't cached
+/// Cached problems are refetched if `refresh` is set or if they are missing released data
+/// With JSON output, failures are returned as typed errors so they get their own error code
 pub async fn get_problem<'a, T: FnOnce(Problem) -> R, R: Future<Output = super::Result> + 'a>(
     id_param: Option<u64>,
     client: &HttpClient,
     store: &'a DataStore,
     multi: &MultiProgress,
+    refresh: bool,
+    format: OutputFormat,
     cb: T,
 ) -> super::Result {
     let id = if let Some(id) = id_param {
@@ -122,47 +306,71 @@ pub async fn get_problem<'a, T: FnOnce(Problem) -> R, R: Future<Output = super::
     let status = StatusSpinner::new("Loading problem...", &multi);
 
     // check cache first
-    if let Some(cached_problem) = store.get_cache(id).await? {
-        // Print problem header
-        status.finish(
-            &format!(
-                "Loaded {}",
-                style(format!("problem {}", cached_problem.id))
-                    .bold()
-                    .bright()
-                    .cyan()
-            ),
-            true,
-        );
-
-        cb(cached_problem.clone()).await?;
-    } else {
-        match client.get_problem(id).await {
-            Ok(problem) => {
-                // Print problem header
-                status.finish(
-                    &format!(
-                        "Loaded {}",
-                        style(format!("problem {}", problem.id))
-                            .bold()
-                            .bright()
-                            .cyan()
-                    ),
-                    true,
-                );
-
-                // insert into cache
-                store.insert_cache(problem.clone()).await?;
-
-                cb(problem).await?;
-            }
-            Err(HttpClientError::ProblemNotFound) => {
-                status.finish(&format!("Problem {} not found", id), false);
-                return Err(CliError::ExitError);
+    let cached_problem = store
+        .get_cache(id)?
+        .map(|p| (p.problem.clone(), p.needs_refresh()));
+    let problem = match cached_problem {
+        Some((cached_problem, false)) if !refresh => cached_problem,
+        cached_problem => {
+            let fetched = if cached_problem.is_some() && !refresh {
+                // a stale cached problem is still usable, so don't keep the user waiting
+                client
+                    .without_retries(STALE_REFRESH_TIMEOUT)
+                    .get_problem(id)
+                    .await
+            } else {
+                client.get_problem(id).await
+            };
+            match fetched {
+                Ok((problem, raw)) => {
+                    // insert into cache
+                    store.insert_cache(problem.clone(), &raw, false).await?;
+                    problem
+                }
+                Err(HttpClientError::ProblemNotFound) => {
+                    status.finish(&format!("Problem {} not found", id), false);
+                    return Err(match format {
+                        OutputFormat::Json => HttpClientError::ProblemNotFound.into(),
+                        OutputFormat::Text => CliError::ExitError,
+                    });
+                }
+                // fall back to the cached problem if refreshing fails
+                Err(e) if cached_problem.is_some() => {
+                    if refresh {
+                        warn!("Could not refresh problem {}: {}", id, e);
+                    } else {
+                        debug!("Could not refresh problem {}: {}", id, e);
+                    }
+                    cached_problem.unwrap().0
+                }
+                Err(HttpClientError::Offline) => {
+                    status.finish(
+                        &format!("Problem {} is not cached and offline mode is enabled", id),
+                        false,
+                    );
+                    return Err(match format {
+                        OutputFormat::Json => HttpClientError::Offline.into(),
+                        OutputFormat::Text => CliError::ExitError,
+                    });
+                }
+                Err(e) => Err(e)?,
             }
-            Err(e) => Err(e)?,
         }
-    }
+    };
+
+    // Print problem header
+    status.finish(
+        &format!(
+            "Loaded {}",
+            style(format!("problem {}", problem.id))
+                .bold()
+                .bright()
+                .cyan()
+        ),
+        true,
+    );
+
+    cb(problem).await?;
 
     Ok(())
 }
@@ -209,13 +417,89 @@ pub async fn handle(
     client: HttpClient,
     store: &DataStore,
     multi: MultiProgress,
+    pager: bool,
+    format: OutputFormat,
 ) -> super::Result {
     match command {
-        Command::Info { id } => {
-            get_problem(id, &client, store, &multi, |problem| async move {
-                print_problem(&problem);
-                Ok(())
-            })
+        Command::Info { id, refresh } => {
+            get_problem(
+                id,
+                &client,
+                store,
+                &multi,
+                refresh,
+                format,
+                |problem| async move {
+                    match format {
+                        OutputFormat::Json => print_json(&problem),
+                        OutputFormat::Text => print_problem(&problem, pager).await,
+                    }
+                },
+            )
+            .await?;
+        }
+        Command::Samples { id, output } => {
+            get_problem(
+                id,
+                &client,
+                store,
+                &multi,
+                false,
+                format,
+                |problem| async move {
+                    create_dir_all(&output).await?;
+                    for (i, case) in problem.test_cases.iter().enumerate() {
+                        write(output.join(format!("{}.in", i + 1)), &case.input).await?;
+                        write(output.join(format!("{}.out", i + 1)), &case.output).await?;
+                    }
+                    println!(
+                        "{}",
+                        style(format!(
+                            "Wrote {} samples to {}",
+                            problem.test_cases.len(),
+                            output.display()
+                        ))
+                        .green()
+                        .bold()
+                    );
+                    Ok(())
+                },
+            )
+            .await?;
+        }
+        Command::Export { id, to, output } => {
+            let client = &client;
+            get_problem(
+                id,
+                client,
+                store,
+                &multi,
+                false,
+                format,
+                |mut problem| async move {
+                    // links and images should work outside of usaco.org
+                    problem.description.resolve_urls(|url| {
+                        if url.contains("://") {
+                            url.into()
+                        } else {
+                            client.url(url)
+                        }
+                    });
+                    let exported = to.render(&problem, &client.problem_url(problem.id));
+                    if let Some(output) = output {
+                        write(&output, exported).await?;
+                        println!(
+                            "{}",
+                            style(format!("Exported to {}", output.display()))
+                                .green()
+                                .bold()
+                        );
+                    } else {
+                        print!("{}", exported);
+                    }
+                    Ok(())
+                },
+            )
             .await?;
         }
         Command::Open {
@@ -235,7 +519,7 @@ pub async fn handle(
                     .unwrap()
             };
 
-            let problem_url = format!("https://usaco.org/index.php?page=viewproblem2&cpid={}", id);
+            let problem_url = client.problem_url(id);
 
             if no_launch_browser {
                 // print a plain url
@@ -244,6 +528,148 @@ pub async fn handle(
                 open_url(&problem_url)?;
             }
         }
+        Command::Prefetch {
+            ids,
+            contest,
+            division,
+            test_data,
+            pin,
+            refresh,
+        } => {
+            let division = division.as_deref().and_then(Division::from_str);
+            let mut ids = ids.into_iter().collect::<IndexSet<_>>();
+
+            if let Some(contest) = contest {
+                let status = StatusSpinner::new("Loading contest...", &multi);
+                match client.get_contest_problems(&contest).await {
+                    Ok(problems) => {
+                        status.finish(
+                            &format!("Found {} problems in {}", problems.len(), contest),
+                            true,
+                        );
+                        // keep problems whose division is unknown for now, they are checked after fetching
+                        ids.extend(
+                            problems
+                                .into_iter()
+                                .filter(|(d, _)| {
+                                    division.is_none() || d.is_none() || *d == division
+                                })
+                                .map(|(_, id)| id),
+                        );
+                    }
+                    Err(HttpClientError::ContestNotFound) => {
+                        status.finish(&format!("Contest \"{}\" not found", contest), false);
+                        return Err(CliError::ExitError);
+                    }
+                    Err(e) => Err(e)?,
+                }
+            }
+
+            let capacity = store.read()?.cache_size.unwrap_or(DEFAULT_CACHE_SIZE);
+            if !pin && capacity > 0 && ids.len() > capacity {
+                warn!(
+                    "Prefetching {} problems but the cache only holds {}. Use --pin or raise the cache size to keep all of them",
+                    ids.len(),
+                    capacity
+                );
+            }
+
+            let bar = multi.add(ProgressBar::new(ids.len() as u64));
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{msg} {bar:30.cyan/blue} {pos}/{len}")
+                    .unwrap(),
+            );
+            bar.set_message(
+                style("Prefetching problems...")
+                    .yellow()
+                    .bright()
+                    .to_string(),
+            );
+
+            let client = &client;
+            let mut results = stream::iter(ids.iter().copied())
+                .map(|id| async move {
+                    (
+                        id,
+                        prefetch_problem(id, client, store, refresh, test_data).await,
+                    )
+                })
+                .buffer_unordered(PREFETCH_CONCURRENCY);
+
+            let mut prefetched = vec![];
+            let mut skipped = 0;
+            while let Some((id, result)) = results.next().await {
+                bar.inc(1);
+                let result = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        warn!("Could not prefetch problem {}: {}", id, e);
+                        continue;
+                    }
+                };
+                if division.is_some_and(|d| d != result.problem.division) {
+                    debug!("Skipping problem {} from another division", id);
+                    skipped += 1;
+                    continue;
+                }
+                // pin as we go so later problems can't evict earlier ones
+                match result.raw {
+                    Some(raw) => store.insert_cache(result.problem, &raw, pin).await?,
+                    None if pin => {
+                        store.set_pinned(&[id], true).await?;
+                    }
+                    None => {}
+                }
+                if let Some(data) = result.test_data {
+                    store.save_test_data(id, &data).await?;
+                }
+                prefetched.push(id);
+            }
+            bar.finish_and_clear();
+            multi.remove(&bar);
+
+            // only count problems that weren't evicted by later ones
+            {
+                let cache = store.get_full_cache()?;
+                prefetched.retain(|id| cache.contains_key(id));
+            }
+
+            let total = ids.len() - skipped;
+            let status = StatusSpinner::new("", &multi);
+            status.finish(
+                &format!("Prefetched {}/{} problems", prefetched.len(), total),
+                prefetched.len() == total,
+            );
+        }
+        Command::Cache {
+            command: CacheCommand::List,
+        } if format == OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct CacheEntry<'a> {
+                id: u64,
+                name: &'a str,
+                contest: &'a str,
+                division: Division,
+                pinned: bool,
+                /// unix timestamp, 0 if unknown
+                fetched_at: u64,
+            }
+
+            let items = store.get_full_cache()?;
+            let entries = items
+                .values()
+                .map(|value| CacheEntry {
+                    id: value.problem.id,
+                    name: &value.problem.name,
+                    contest: &value.problem.contest,
+                    division: value.problem.division,
+                    pinned: value.pinned,
+                    fetched_at: value.fetched_at,
+                })
+                .collect::<Vec<_>>();
+            print_json(&entries)?;
+        }
         Command::Cache {
             command: CacheCommand::List,
         } => {
@@ -252,7 +678,7 @@ pub async fn handle(
             println!("{}", style("Cached problems:").bold().cyan());
             for (i, value) in items.values().enumerate() {
                 println!(
-                    "{} {} {}",
+                    "{} {} {} {}{}",
                     style(format!("{}:", i + 1))
                         .bold()
                         // color the index based on the recency
@@ -261,8 +687,14 @@ pub async fn handle(
                             3..6 => Color::Yellow,
                             _ => Color::Red,
                         }),
-                    value.name,
-                    style(format!("({})", value.id)).magenta()
+                    value.problem.name,
+                    style(format!("({})", value.problem.id)).magenta(),
+                    style(format_age(value.fetched_at)).dim(),
+                    if value.pinned {
+                        style(" [pinned]").cyan().bold().to_string()
+                    } else {
+                        String::new()
+                    }
                 );
             }
         }
@@ -280,6 +712,28 @@ pub async fn handle(
                 }
             }
         }
+        Command::Cache {
+            command: CacheCommand::Pin { problem_ids },
+        } => {
+            let count = store.set_pinned(&problem_ids, true).await?;
+            println!(
+                "{}",
+                style(format!("Successfully pinned {} items.", count))
+                    .green()
+                    .bold()
+            );
+        }
+        Command::Cache {
+            command: CacheCommand::Unpin { problem_ids },
+        } => {
+            let count = store.set_pinned(&problem_ids, false).await?;
+            println!(
+                "{}",
+                style(format!("Successfully unpinned {} items.", count))
+                    .green()
+                    .bold()
+            );
+        }
         Command::Cache {
             command: CacheCommand::Clear { problem_ids },
         } => {
@@ -0,0 +1,92 @@
+use crate::http_client::{Block, Document, Inline, Problem};
+
+/// Render a problem statement as a standalone HTML page
+/// Math is kept as `$...$` and typeset by MathJax when it can be loaded
+pub fn render_html(problem: &Problem, source_url: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{name}</title>
+<script>MathJax = {{ tex: {{ inlineMath: [["$", "$"]] }} }};</script>
+<script async src="https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js"></script>
+<style>
+body {{ max-width: 50em; margin: 2em auto; padding: 0 1em; font-family: sans-serif; line-height: 1.5; }}
+pre {{ background: #f4f4f4; padding: 0.5em; }}
+.credits {{ font-style: italic; }}
+</style>
+</head>
+<body>
+<h1>{name}</h1>
+<p>USACO {contest}, {division}: Problem {num}</p>
+<p>Source: <a href="{url}">{url}</a></p>
+{body}
+</body>
+</html>
+"#,
+        name = escape(&problem.name),
+        contest = escape(&problem.contest),
+        division = problem.division.name(),
+        num = problem.problem_num,
+        url = escape(source_url),
+        body = render_document(&problem.description)
+    )
+}
+
+fn render_document(doc: &Document) -> String {
+    doc.blocks
+        .iter()
+        .map(render_block)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_block(block: &Block) -> String {
+    match block {
+        Block::Heading(inlines) => format!("<h3>{}</h3>", render_inlines(inlines)),
+        Block::Paragraph(inlines) => format!("<p>{}</p>", render_inlines(inlines)),
+        Block::List(items) => format!(
+            "<ul>\n{}\n</ul>",
+            items
+                .iter()
+                .map(|item| format!("<li>{}</li>", render_inlines(item)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+        Block::Pre(text) => format!("<pre>{}</pre>", escape(text)),
+        Block::Image { src, alt } => format!(
+            r#"<img src="{}" alt="{}">"#,
+            escape(src),
+            escape(alt.as_deref().unwrap_or(""))
+        ),
+        Block::Credits(text) => format!(r#"<p class="credits">{}</p>"#, escape(text)),
+    }
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Math(tex) => format!("${}$", escape(tex)),
+            Inline::Bold(content) => format!("<strong>{}</strong>", render_inlines(content)),
+            Inline::Italic(content) => format!("<em>{}</em>", render_inlines(content)),
+            Inline::Code(code) => format!("<code>{}</code>", escape(code)),
+            Inline::Link { href, content } => {
+                format!(
+                    r#"<a href="{}">{}</a>"#,
+                    escape(href),
+                    render_inlines(content)
+                )
+            }
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,65 @@
+use crate::http_client::{Block, Document, Inline, Problem};
+
+/// Render a problem statement as Markdown, keeping math as `$...$`
+pub fn render_markdown(problem: &Problem, source_url: &str) -> String {
+    format!(
+        "# {}\n\nUSACO {}, {}: Problem {}\n\nSource: <{}>\n\n{}\n",
+        escape(&problem.name),
+        problem.contest,
+        problem.division.name(),
+        problem.problem_num,
+        source_url,
+        render_document(&problem.description)
+    )
+}
+
+fn render_document(doc: &Document) -> String {
+    doc.blocks
+        .iter()
+        .map(render_block)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render_block(block: &Block) -> String {
+    match block {
+        Block::Heading(inlines) => format!("### {}", render_inlines(inlines)),
+        Block::Paragraph(inlines) => render_inlines(inlines),
+        Block::List(items) => items
+            .iter()
+            .map(|item| format!("- {}", render_inlines(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Block::Pre(text) => format!("```\n{}\n```", text.trim_end_matches('\n')),
+        Block::Image { src, alt } => {
+            format!("![{}]({})", escape(alt.as_deref().unwrap_or("")), src)
+        }
+        Block::Credits(text) => format!("*{}*", escape(text)),
+    }
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Math(tex) => format!("${}$", tex),
+            Inline::Bold(content) => format!("**{}**", render_inlines(content)),
+            Inline::Italic(content) => format!("*{}*", render_inlines(content)),
+            Inline::Code(code) => format!("`{}`", code),
+            Inline::Link { href, content } => format!("[{}]({})", render_inlines(content), href),
+        })
+        .collect()
+}
+
+/// escape characters Markdown would treat as formatting
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '$' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
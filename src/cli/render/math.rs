@@ -0,0 +1,748 @@
+/// How an atom is spaced relative to its neighbors, following TeX's atom classes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Ord,
+    /// large operators and function names like `\sum` and `\log`
+    Op,
+    Bin,
+    Rel,
+    Open,
+    Close,
+    Punct,
+    /// `\ldots` and friends
+    Inner,
+}
+
+struct Atom {
+    kind: Kind,
+    text: String,
+}
+
+impl Atom {
+    fn new(kind: Kind, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// `\name`, or a single symbol like `\{`
+    Command(String),
+    Char(char),
+    Space,
+    Open,
+    Close,
+    Sup,
+    Sub,
+}
+
+/// Render TeX math as plain Unicode text for the terminal
+pub fn render_math(tex: &str) -> String {
+    let mut parser = Parser {
+        tokens: tokenize(tex),
+        pos: 0,
+        script: false,
+    };
+    let mut atoms = parser.parse_list();
+    // an unmatched closing brace ends the list early, so skip it and keep going
+    while parser.pos < parser.tokens.len() {
+        atoms.extend(parser.parse_list());
+    }
+    join_atoms(&atoms, false).trim().into()
+}
+
+fn tokenize(tex: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = tex.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    match chars.next() {
+                        Some(c) => name.push(c),
+                        None => continue,
+                    }
+                }
+                Token::Command(name)
+            }
+            '{' => Token::Open,
+            '}' => Token::Close,
+            '^' => Token::Sup,
+            '_' => Token::Sub,
+            '~' => Token::Command(" ".into()),
+            c if c.is_whitespace() => Token::Space,
+            c => Token::Char(c),
+        });
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// inside a superscript, subscript, or fraction, where TeX doesn't add spacing
+    script: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    /// parse atoms until a closing brace or the end of input
+    fn parse_list(&mut self) -> Vec<Atom> {
+        let mut atoms: Vec<Atom> = vec![];
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Close => {
+                    self.pos += 1;
+                    break;
+                }
+                Token::Space => self.pos += 1,
+                Token::Sup | Token::Sub => {
+                    let sup = *token == Token::Sup;
+                    self.pos += 1;
+                    let script = self.parse_script_arg();
+                    let script = render_script(&script, sup);
+                    match atoms.last_mut() {
+                        Some(atom) => atom.text.push_str(&script),
+                        None => atoms.push(Atom::new(Kind::Ord, script)),
+                    }
+                }
+                _ => atoms.extend(self.parse_atom()),
+            }
+        }
+        atoms
+    }
+
+    /// parse a group or a single atom
+    fn parse_arg(&mut self) -> Vec<Atom> {
+        self.skip_spaces();
+        match self.peek() {
+            Some(Token::Open) => {
+                self.pos += 1;
+                self.parse_list()
+            }
+            Some(Token::Close) | None => vec![],
+            Some(_) => self.parse_atom().into_iter().collect(),
+        }
+    }
+
+    /// parse an argument without spacing and render it
+    fn parse_script_arg(&mut self) -> String {
+        let script = std::mem::replace(&mut self.script, true);
+        let atoms = self.parse_arg();
+        self.script = script;
+        join_atoms(&atoms, true)
+    }
+
+    /// parse the raw text of an argument, for commands like `\text`
+    fn parse_text_arg(&mut self) -> String {
+        self.skip_spaces();
+        if self.peek() != Some(&Token::Open) {
+            return match self.next() {
+                Some(Token::Char(c)) => c.to_string(),
+                _ => String::new(),
+            };
+        }
+        self.pos += 1;
+        let mut text = String::new();
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Open => depth += 1,
+                Token::Close if depth == 0 => break,
+                Token::Close => depth -= 1,
+                Token::Space => text.push(' '),
+                Token::Char(c) => text.push(c),
+                Token::Command(name) => text.push_str(&name),
+                Token::Sup => text.push('^'),
+                Token::Sub => text.push('_'),
+            }
+        }
+        text
+    }
+
+    /// parse an optional `[...]` argument
+    fn parse_optional_arg(&mut self) -> Option<String> {
+        self.skip_spaces();
+        if self.peek() != Some(&Token::Char('[')) {
+            return None;
+        }
+        self.pos += 1;
+        let mut atoms = vec![];
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Char(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Token::Space => self.pos += 1,
+                _ => atoms.extend(self.parse_atom()),
+            }
+        }
+        Some(join_atoms(&atoms, true))
+    }
+
+    fn parse_atom(&mut self) -> Option<Atom> {
+        match self.next()? {
+            Token::Open => {
+                let atoms = self.parse_list();
+                Some(Atom::new(Kind::Ord, join_atoms(&atoms, self.script)))
+            }
+            Token::Char(c) => Some(char_atom(c)),
+            Token::Command(name) => self.parse_command(&name),
+            Token::Close | Token::Space | Token::Sup | Token::Sub => None,
+        }
+    }
+
+    fn parse_command(&mut self, name: &str) -> Option<Atom> {
+        if let Some((kind, text)) = symbol(name) {
+            return Some(Atom::new(kind, text));
+        }
+        let atom = match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.parse_script_arg();
+                let den = self.parse_script_arg();
+                Atom::new(
+                    Kind::Ord,
+                    format!("{}/{}", parenthesize(&num), parenthesize(&den)),
+                )
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let n = self.parse_script_arg();
+                let k = self.parse_script_arg();
+                Atom::new(Kind::Ord, format!("C({}, {})", n, k))
+            }
+            "sqrt" => {
+                let index = self.parse_optional_arg();
+                let radicand = self.parse_script_arg();
+                let index = index.map(|i| to_superscript(&i).unwrap_or(i));
+                Atom::new(
+                    Kind::Ord,
+                    format!("{}√{}", index.unwrap_or_default(), parenthesize(&radicand)),
+                )
+            }
+            "text" | "textrm" | "textit" | "textbf" | "texttt" | "textsf" | "mbox" | "mathrm"
+            | "mathit" | "mathbf" | "mathsf" | "mathtt" | "mathcal" | "mathscr" | "mathfrak"
+            | "boldsymbol" => Atom::new(Kind::Ord, self.parse_text_arg()),
+            "operatorname" => Atom::new(Kind::Op, self.parse_text_arg()),
+            "mathbb" => Atom::new(
+                Kind::Ord,
+                self.parse_text_arg()
+                    .chars()
+                    .map(|c| match c {
+                        'N' => 'ℕ',
+                        'Z' => 'ℤ',
+                        'Q' => 'ℚ',
+                        'R' => 'ℝ',
+                        'C' => 'ℂ',
+                        c => c,
+                    })
+                    .collect::<String>(),
+            ),
+            "overline" | "bar" => self.combining('\u{305}'),
+            "underline" => self.combining('\u{332}'),
+            "hat" | "widehat" => self.combining('\u{302}'),
+            "tilde" | "widetilde" => self.combining('\u{303}'),
+            "vec" | "overrightarrow" => self.combining('\u{20d7}'),
+            "dot" => self.combining('\u{307}'),
+            "pmod" => {
+                let m = self.parse_script_arg();
+                Atom::new(Kind::Op, format!("(mod {})", m))
+            }
+            "not" => {
+                let mut atom = self.parse_arg().into_iter().next()?;
+                atom.text = match atom.text.as_str() {
+                    "=" => "≠".into(),
+                    "∈" => "∉".into(),
+                    "<" => "≮".into(),
+                    ">" => "≯".into(),
+                    "≤" => "≰".into(),
+                    "≥" => "≱".into(),
+                    "⊂" => "⊄".into(),
+                    "⊆" => "⊈".into(),
+                    _ => format!("{}\u{338}", atom.text),
+                };
+                atom.kind = Kind::Rel;
+                atom
+            }
+            // delimiter sizing, `\left.` is an invisible delimiter
+            "left" | "right" | "middle" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr"
+            | "Bigl" | "Bigr" | "biggl" | "biggr" | "Biggl" | "Biggr" => {
+                self.skip_spaces();
+                if self.peek() == Some(&Token::Char('.')) {
+                    self.pos += 1;
+                }
+                return None;
+            }
+            "displaystyle" | "textstyle" | "scriptstyle" | "limits" | "nolimits" | "!" => {
+                return None;
+            }
+            // unknown commands keep their name rather than disappearing
+            name => Atom::new(Kind::Ord, name),
+        };
+        Some(atom)
+    }
+
+    /// apply a combining accent to every character of the argument
+    fn combining(&mut self, mark: char) -> Atom {
+        let text = self.parse_script_arg();
+        Atom::new(
+            Kind::Ord,
+            text.chars().flat_map(|c| [c, mark]).collect::<String>(),
+        )
+    }
+}
+
+fn char_atom(c: char) -> Atom {
+    let (kind, text) = match c {
+        '+' | '*' => (Kind::Bin, c.to_string()),
+        '-' => (Kind::Bin, "−".into()),
+        '=' | '<' | '>' | ':' => (Kind::Rel, c.to_string()),
+        '(' | '[' => (Kind::Open, c.to_string()),
+        ')' | ']' => (Kind::Close, c.to_string()),
+        ',' | ';' => (Kind::Punct, c.to_string()),
+        '\'' => (Kind::Ord, "′".into()),
+        c => (Kind::Ord, c.to_string()),
+    };
+    Atom::new(kind, text)
+}
+
+/// commands that are just a symbol
+fn symbol(name: &str) -> Option<(Kind, &'static str)> {
+    use Kind::*;
+    Some(match name {
+        // lowercase greek
+        "alpha" => (Ord, "α"),
+        "beta" => (Ord, "β"),
+        "gamma" => (Ord, "γ"),
+        "delta" => (Ord, "δ"),
+        "epsilon" => (Ord, "ϵ"),
+        "varepsilon" => (Ord, "ε"),
+        "zeta" => (Ord, "ζ"),
+        "eta" => (Ord, "η"),
+        "theta" => (Ord, "θ"),
+        "vartheta" => (Ord, "ϑ"),
+        "iota" => (Ord, "ι"),
+        "kappa" => (Ord, "κ"),
+        "lambda" => (Ord, "λ"),
+        "mu" => (Ord, "μ"),
+        "nu" => (Ord, "ν"),
+        "xi" => (Ord, "ξ"),
+        "omicron" => (Ord, "ο"),
+        "pi" => (Ord, "π"),
+        "varpi" => (Ord, "ϖ"),
+        "rho" => (Ord, "ρ"),
+        "varrho" => (Ord, "ϱ"),
+        "sigma" => (Ord, "σ"),
+        "varsigma" => (Ord, "ς"),
+        "tau" => (Ord, "τ"),
+        "upsilon" => (Ord, "υ"),
+        "phi" => (Ord, "ϕ"),
+        "varphi" => (Ord, "φ"),
+        "chi" => (Ord, "χ"),
+        "psi" => (Ord, "ψ"),
+        "omega" => (Ord, "ω"),
+        // uppercase greek
+        "Gamma" => (Ord, "Γ"),
+        "Delta" => (Ord, "Δ"),
+        "Theta" => (Ord, "Θ"),
+        "Lambda" => (Ord, "Λ"),
+        "Xi" => (Ord, "Ξ"),
+        "Pi" => (Ord, "Π"),
+        "Sigma" => (Ord, "Σ"),
+        "Upsilon" => (Ord, "Υ"),
+        "Phi" => (Ord, "Φ"),
+        "Psi" => (Ord, "Ψ"),
+        "Omega" => (Ord, "Ω"),
+        // relations
+        "le" | "leq" => (Rel, "≤"),
+        "ge" | "geq" => (Rel, "≥"),
+        "leqslant" => (Rel, "⩽"),
+        "geqslant" => (Rel, "⩾"),
+        "ne" | "neq" => (Rel, "≠"),
+        "lt" => (Rel, "<"),
+        "gt" => (Rel, ">"),
+        "approx" => (Rel, "≈"),
+        "equiv" => (Rel, "≡"),
+        "sim" => (Rel, "∼"),
+        "simeq" => (Rel, "≃"),
+        "cong" => (Rel, "≅"),
+        "propto" => (Rel, "∝"),
+        "ll" => (Rel, "≪"),
+        "gg" => (Rel, "≫"),
+        "prec" => (Rel, "≺"),
+        "succ" => (Rel, "≻"),
+        "preceq" => (Rel, "⪯"),
+        "succeq" => (Rel, "⪰"),
+        "in" => (Rel, "∈"),
+        "notin" => (Rel, "∉"),
+        "ni" => (Rel, "∋"),
+        "subset" => (Rel, "⊂"),
+        "subseteq" => (Rel, "⊆"),
+        "subsetneq" => (Rel, "⊊"),
+        "supset" => (Rel, "⊃"),
+        "supseteq" => (Rel, "⊇"),
+        "to" | "rightarrow" => (Rel, "→"),
+        "gets" | "leftarrow" => (Rel, "←"),
+        "leftrightarrow" => (Rel, "↔"),
+        "Rightarrow" => (Rel, "⇒"),
+        "Leftarrow" => (Rel, "⇐"),
+        "Leftrightarrow" => (Rel, "⇔"),
+        "implies" => (Rel, "⟹"),
+        "iff" => (Rel, "⟺"),
+        "mapsto" => (Rel, "↦"),
+        "mid" => (Rel, "∣"),
+        "nmid" => (Rel, "∤"),
+        "parallel" => (Rel, "∥"),
+        "perp" => (Rel, "⊥"),
+        "coloneqq" => (Rel, "≔"),
+        // binary operators
+        "times" => (Bin, "×"),
+        "div" => (Bin, "÷"),
+        "cdot" => (Bin, "⋅"),
+        "pm" => (Bin, "±"),
+        "mp" => (Bin, "∓"),
+        "cup" => (Bin, "∪"),
+        "cap" => (Bin, "∩"),
+        "setminus" => (Bin, "∖"),
+        "land" | "wedge" => (Bin, "∧"),
+        "lor" | "vee" => (Bin, "∨"),
+        "oplus" => (Bin, "⊕"),
+        "otimes" => (Bin, "⊗"),
+        "circ" => (Bin, "∘"),
+        "ast" => (Bin, "∗"),
+        "star" => (Bin, "⋆"),
+        "bullet" => (Bin, "∙"),
+        "bmod" | "mod" => (Bin, "mod"),
+        // large operators
+        "sum" => (Op, "∑"),
+        "prod" => (Op, "∏"),
+        "coprod" => (Op, "∐"),
+        "int" => (Op, "∫"),
+        "oint" => (Op, "∮"),
+        "bigcup" => (Op, "⋃"),
+        "bigcap" => (Op, "⋂"),
+        "bigoplus" => (Op, "⨁"),
+        "bigwedge" => (Op, "⋀"),
+        "bigvee" => (Op, "⋁"),
+        // functions
+        "log" => (Op, "log"),
+        "ln" => (Op, "ln"),
+        "lg" => (Op, "lg"),
+        "exp" => (Op, "exp"),
+        "sin" => (Op, "sin"),
+        "cos" => (Op, "cos"),
+        "tan" => (Op, "tan"),
+        "max" => (Op, "max"),
+        "min" => (Op, "min"),
+        "sup" => (Op, "sup"),
+        "inf" => (Op, "inf"),
+        "lim" => (Op, "lim"),
+        "arg" => (Op, "arg"),
+        "argmax" => (Op, "argmax"),
+        "argmin" => (Op, "argmin"),
+        "det" => (Op, "det"),
+        "dim" => (Op, "dim"),
+        "gcd" => (Op, "gcd"),
+        "lcm" => (Op, "lcm"),
+        "deg" => (Op, "deg"),
+        "Pr" => (Op, "Pr"),
+        // delimiters
+        "lfloor" => (Open, "⌊"),
+        "rfloor" => (Close, "⌋"),
+        "lceil" => (Open, "⌈"),
+        "rceil" => (Close, "⌉"),
+        "langle" => (Open, "⟨"),
+        "rangle" => (Close, "⟩"),
+        "lvert" => (Open, "|"),
+        "rvert" => (Close, "|"),
+        "lVert" => (Open, "‖"),
+        "rVert" => (Close, "‖"),
+        "{" | "lbrace" => (Open, "{"),
+        "}" | "rbrace" => (Close, "}"),
+        "vert" => (Ord, "|"),
+        "|" | "Vert" => (Ord, "‖"),
+        // dots
+        "dots" | "ldots" | "dotsc" | "dotsb" => (Inner, "…"),
+        "cdots" => (Inner, "⋯"),
+        "vdots" => (Ord, "⋮"),
+        "ddots" => (Ord, "⋱"),
+        // other symbols
+        "infty" => (Ord, "∞"),
+        "emptyset" | "varnothing" => (Ord, "∅"),
+        "forall" => (Ord, "∀"),
+        "exists" => (Ord, "∃"),
+        "nexists" => (Ord, "∄"),
+        "neg" | "lnot" => (Ord, "¬"),
+        "partial" => (Ord, "∂"),
+        "nabla" => (Ord, "∇"),
+        "prime" => (Ord, "′"),
+        "ell" => (Ord, "ℓ"),
+        "hbar" => (Ord, "ℏ"),
+        "aleph" => (Ord, "ℵ"),
+        "angle" => (Ord, "∠"),
+        "triangle" => (Ord, "△"),
+        "square" => (Ord, "□"),
+        "dagger" => (Ord, "†"),
+        "backslash" => (Ord, "\\"),
+        "colon" => (Punct, ":"),
+        "%" => (Ord, "%"),
+        "$" => (Ord, "$"),
+        "&" => (Ord, "&"),
+        "#" => (Ord, "#"),
+        "_" => (Ord, "_"),
+        // spacing
+        "," | ":" | ";" | ">" | " " => (Ord, " "),
+        "quad" => (Ord, "  "),
+        "qquad" => (Ord, "    "),
+        "\\" => (Ord, " "),
+        _ => return None,
+    })
+}
+
+/// join atoms with TeX-like spacing. Scripts are never spaced
+fn join_atoms(atoms: &[Atom], script: bool) -> String {
+    use Kind::*;
+    let mut out = String::new();
+    let mut prev: Option<Kind> = None;
+    for atom in atoms {
+        let mut kind = atom.kind;
+        // a binary operator with nothing on its left is unary, like -1
+        if kind == Bin && matches!(prev, None | Some(Bin | Rel | Open | Punct | Op)) {
+            kind = Ord;
+        }
+        if let Some(prev) = prev.filter(|_| !script) {
+            let space = match (prev, kind) {
+                (Rel, Close | Punct) | (Open, Rel) => false,
+                (Rel, _) | (_, Rel) | (Bin, _) | (_, Bin) | (Punct, _) => true,
+                (Inner, Close | Punct) | (Open, Inner) => false,
+                (Inner, _) | (_, Inner) => true,
+                (Op, Ord | Op) | (Ord | Close, Op) => true,
+                _ => false,
+            };
+            if space {
+                out.push(' ');
+            }
+        }
+        out.push_str(&atom.text);
+        prev = Some(kind);
+    }
+    out
+}
+
+/// wrap anything more complicated than a single number or name in parentheses
+fn parenthesize(text: &str) -> String {
+    if text.chars().all(|c| c.is_alphanumeric()) {
+        text.into()
+    } else {
+        format!("({})", text)
+    }
+}
+
+/// render a superscript or subscript, falling back to `^` or `_` notation
+fn render_script(text: &str, sup: bool) -> String {
+    let converted = if sup {
+        to_superscript(text)
+    } else {
+        to_subscript(text)
+    };
+    match converted {
+        Some(converted) => converted,
+        None => {
+            let marker = if sup { '^' } else { '_' };
+            if text.chars().count() == 1 {
+                format!("{}{}", marker, text)
+            } else {
+                format!("{}({})", marker, text)
+            }
+        }
+    }
+}
+
+fn to_superscript(text: &str) -> Option<String> {
+    text.chars()
+        .map(|c| {
+            Some(match c {
+                '0' => '⁰',
+                '1' => '¹',
+                '2' => '²',
+                '3' => '³',
+                '4' => '⁴',
+                '5' => '⁵',
+                '6' => '⁶',
+                '7' => '⁷',
+                '8' => '⁸',
+                '9' => '⁹',
+                '+' => '⁺',
+                '−' | '-' => '⁻',
+                '=' => '⁼',
+                '(' => '⁽',
+                ')' => '⁾',
+                'a' => 'ᵃ',
+                'b' => 'ᵇ',
+                'c' => 'ᶜ',
+                'd' => 'ᵈ',
+                'e' => 'ᵉ',
+                'f' => 'ᶠ',
+                'g' => 'ᵍ',
+                'h' => 'ʰ',
+                'i' => 'ⁱ',
+                'j' => 'ʲ',
+                'k' => 'ᵏ',
+                'l' => 'ˡ',
+                'm' => 'ᵐ',
+                'n' => 'ⁿ',
+                'o' => 'ᵒ',
+                'p' => 'ᵖ',
+                'r' => 'ʳ',
+                's' => 'ˢ',
+                't' => 'ᵗ',
+                'u' => 'ᵘ',
+                'v' => 'ᵛ',
+                'w' => 'ʷ',
+                'x' => 'ˣ',
+                'y' => 'ʸ',
+                'z' => 'ᶻ',
+                'A' => 'ᴬ',
+                'B' => 'ᴮ',
+                'D' => 'ᴰ',
+                'E' => 'ᴱ',
+                'G' => 'ᴳ',
+                'H' => 'ᴴ',
+                'I' => 'ᴵ',
+                'J' => 'ᴶ',
+                'K' => 'ᴷ',
+                'L' => 'ᴸ',
+                'M' => 'ᴹ',
+                'N' => 'ᴺ',
+                'O' => 'ᴼ',
+                'P' => 'ᴾ',
+                'R' => 'ᴿ',
+                'T' => 'ᵀ',
+                'U' => 'ᵁ',
+                'V' => 'ⱽ',
+                'W' => 'ᵂ',
+                // already raised
+                '′' | '*' | '∗' => c,
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+fn to_subscript(text: &str) -> Option<String> {
+    text.chars()
+        .map(|c| {
+            Some(match c {
+                '0' => '₀',
+                '1' => '₁',
+                '2' => '₂',
+                '3' => '₃',
+                '4' => '₄',
+                '5' => '₅',
+                '6' => '₆',
+                '7' => '₇',
+                '8' => '₈',
+                '9' => '₉',
+                '+' => '₊',
+                '−' | '-' => '₋',
+                '=' => '₌',
+                '(' => '₍',
+                ')' => '₎',
+                'a' => 'ₐ',
+                'e' => 'ₑ',
+                'h' => 'ₕ',
+                'i' => 'ᵢ',
+                'j' => 'ⱼ',
+                'k' => 'ₖ',
+                'l' => 'ₗ',
+                'm' => 'ₘ',
+                'n' => 'ₙ',
+                'o' => 'ₒ',
+                'p' => 'ₚ',
+                'r' => 'ᵣ',
+                's' => 'ₛ',
+                't' => 'ₜ',
+                'u' => 'ᵤ',
+                'v' => 'ᵥ',
+                'x' => 'ₓ',
+                _ => return None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_math;
+
+    #[test]
+    fn renders_statement_math() {
+        // snippets from real USACO statements
+        let cases = [
+            (r"1 \le N \le 10^5", "1 ≤ N ≤ 10⁵"),
+            (r"\sum_{i=1}^{N} a_i", "∑ᵢ₌₁ᴺ aᵢ"),
+            (r"\frac{N(N-1)}{2}", "(N(N−1))/2"),
+            (r"\lfloor x \rfloor", "⌊x⌋"),
+            // no subscript comma in Unicode
+            (r"a_{i,j}", "a_(i,j)"),
+            (r"\{1,\dots,N\}", "{1, …, N}"),
+        ];
+        for (tex, expected) in cases {
+            assert_eq!(render_math(tex), expected, "rendering {}", tex);
+        }
+    }
+
+    #[test]
+    fn renders_each_construct() {
+        let cases = [
+            (r"\frac{a}{b}", "a/b"),
+            (r"\frac12", "1/2"),
+            (r"\binom{n}{k}", "C(n, k)"),
+            (r"\lfloor N/2 \rfloor", "⌊N/2⌋"),
+            (r"\lceil \frac{N}{2} \rceil", "⌈N/2⌉"),
+            (r"a \not= b", "a ≠ b"),
+            (r"a \neq b", "a ≠ b"),
+            (r"\sqrt{N}", "√N"),
+            (r"\sqrt[3]{N}", "³√N"),
+            (r"\text{if } x", "if x"),
+            (r"x \pmod{10^9+7}", "x (mod 10⁹+7)"),
+            // nested scripts have no Unicode form
+            (r"x_{i_j}", "x_(iⱼ)"),
+            (r"2^{2^k}", "2^(2ᵏ)"),
+            (r"a_{i}^{2}", "aᵢ²"),
+            (r"\weird + 1", "weird + 1"),
+        ];
+        for (tex, expected) in cases {
+            assert_eq!(render_math(tex), expected, "rendering {}", tex);
+        }
+    }
+
+    #[test]
+    fn keeps_input_after_unmatched_brace() {
+        assert_eq!(render_math("a}b"), "ab");
+        assert_eq!(render_math(r"x^{2}} + 1"), "x² + 1");
+    }
+}
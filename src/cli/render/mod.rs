@@ -0,0 +1,166 @@
+mod html;
+mod markdown;
+mod math;
+mod text;
+
+use crate::http_client::{Block, Document, Inline, Problem};
+use clap::ValueEnum;
+use console::{measure_text_width, style};
+use math::render_math;
+use textwrap::Options;
+
+/// Formats problem statements can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown, with math as `$...$`
+    Md,
+    /// Standalone HTML page
+    Html,
+    /// Plain text
+    Txt,
+}
+
+impl ExportFormat {
+    /// Render a problem statement in this format
+    pub fn render(&self, problem: &Problem, source_url: &str) -> String {
+        match self {
+            Self::Md => markdown::render_markdown(problem, source_url),
+            Self::Html => html::render_html(problem, source_url),
+            Self::Txt => text::render_text(problem, source_url),
+        }
+    }
+}
+
+/// Render a document as ansi escape formatted text for the terminal
+/// Text is wrapped to `width` if given, but preformatted blocks are left alone
+/// Sample cases are drawn in numbered boxes
+pub fn render_document(doc: &Document, width: Option<usize>) -> String {
+    let mut parts = vec![];
+    let mut samples = 0;
+    let mut blocks = doc.blocks.as_slice();
+    while !blocks.is_empty() {
+        if let Some((input, output)) = sample_blocks(blocks) {
+            samples += 1;
+            parts.push(String::new());
+            parts.push(render_sample(samples, input, output));
+            parts.push(String::new());
+            blocks = &blocks[4..];
+        } else {
+            parts.push(render_block(&blocks[0], width));
+            blocks = &blocks[1..];
+        }
+    }
+    parts.join("\n")
+}
+
+/// match the "SAMPLE INPUT:" and "SAMPLE OUTPUT:" headings and the blocks after them
+fn sample_blocks(blocks: &[Block]) -> Option<(&str, &str)> {
+    let is_heading = |block: &Block, text: &str| matches!(block, Block::Heading(inlines) if Inline::plain_text(inlines).to_uppercase().starts_with(text));
+    match blocks {
+        [in_heading, Block::Pre(input), out_heading, Block::Pre(output), ..]
+            if is_heading(in_heading, "SAMPLE INPUT")
+                && is_heading(out_heading, "SAMPLE OUTPUT") =>
+        {
+            Some((input, output))
+        }
+        _ => None,
+    }
+}
+
+/// Draw a sample test case in a box
+pub fn render_sample(num: usize, input: &str, output: &str) -> String {
+    let input = input.trim_end_matches('\n');
+    let output = output.trim_end_matches('\n');
+    let title = format!(" Sample {} ", num);
+    let inner = input
+        .lines()
+        .chain(output.lines())
+        .map(measure_text_width)
+        .chain([measure_text_width(&title), "Output".len()])
+        .max()
+        .unwrap_or_default()
+        + 2;
+
+    let border = |s: &str| style(s).dim().to_string();
+    let line = |text: &str, styled: String| {
+        format!(
+            "{} {}{} {}",
+            border("│"),
+            styled,
+            " ".repeat(inner - 2 - measure_text_width(text)),
+            border("│")
+        )
+    };
+    let section = |label: &str, text: &str| {
+        let mut lines = vec![line(label, style(label).bold().cyan().to_string())];
+        lines.extend(
+            text.lines()
+                .map(|l| line(l, style(l).color256(255).to_string())),
+        );
+        lines
+    };
+
+    let mut lines = vec![border(&format!(
+        "╭─{}{}╮",
+        title,
+        "─".repeat(inner - 1 - measure_text_width(&title))
+    ))];
+    lines.extend(section("Input", input));
+    lines.push(border(&format!("├{}┤", "─".repeat(inner))));
+    lines.extend(section("Output", output));
+    lines.push(border(&format!("╰{}╯", "─".repeat(inner))));
+    lines.join("\n")
+}
+
+fn render_block(block: &Block, width: Option<usize>) -> String {
+    match block {
+        Block::Heading(inlines) => format!(
+            "\n{}",
+            style_lines(&wrap(&render_inlines(inlines), width, ""), |line| {
+                style(line).bold().blue().underlined().to_string()
+            })
+        ),
+        Block::Paragraph(inlines) => wrap(&render_inlines(inlines), width, ""),
+        Block::List(items) => items
+            .iter()
+            .map(|item| wrap(&format!(" • {}", render_inlines(item)), width, "   "))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Block::Pre(text) => {
+            style_lines(text, |line| style(line).italic().color256(255).to_string())
+        }
+        Block::Image { src, alt } => style(format!("[image: {}]", alt.as_deref().unwrap_or(src)))
+            .dim()
+            .to_string(),
+        Block::Credits(text) => style_lines(&wrap(text, width, ""), |line| {
+            style(line).magenta().to_string()
+        }),
+    }
+}
+
+/// wrap text, keeping ansi styles intact
+fn wrap(text: &str, width: Option<usize>, indent: &str) -> String {
+    match width {
+        Some(width) => textwrap::fill(text, Options::new(width).subsequent_indent(indent)),
+        None => text.into(),
+    }
+}
+
+/// style each line separately so pagers don't lose the style when scrolling
+fn style_lines(text: &str, f: impl Fn(&str) -> String) -> String {
+    text.split('\n').map(f).collect::<Vec<_>>().join("\n")
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => text.clone(),
+            Inline::Math(tex) => style(render_math(tex)).italic().yellow().to_string(),
+            Inline::Bold(content) => style(render_inlines(content)).bold().to_string(),
+            Inline::Italic(content) => style(render_inlines(content)).italic().to_string(),
+            Inline::Code(code) => style(code).color256(255).to_string(),
+            Inline::Link { content, .. } => style(render_inlines(content)).underlined().to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+use super::render_math;
+use crate::http_client::{Block, Document, Inline, Problem};
+
+/// Render a problem statement as plain text without any escape codes
+pub fn render_text(problem: &Problem, source_url: &str) -> String {
+    format!(
+        "{}\nUSACO {}, {}: Problem {}\nSource: {}\n\n{}\n",
+        problem.name,
+        problem.contest,
+        problem.division.name(),
+        problem.problem_num,
+        source_url,
+        render_document(&problem.description)
+    )
+}
+
+fn render_document(doc: &Document) -> String {
+    doc.blocks
+        .iter()
+        .map(render_block)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render_block(block: &Block) -> String {
+    match block {
+        Block::Heading(inlines) => render_inlines(inlines),
+        Block::Paragraph(inlines) => render_inlines(inlines),
+        Block::List(items) => items
+            .iter()
+            .map(|item| format!(" • {}", render_inlines(item)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Block::Pre(text) => text.trim_end_matches('\n').into(),
+        Block::Image { src, alt } => format!("[image: {}]", alt.as_deref().unwrap_or(src)),
+        Block::Credits(text) => text.clone(),
+    }
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) | Inline::Code(text) => text.clone(),
+            Inline::Math(tex) => render_math(tex),
+            Inline::Bold(content) | Inline::Italic(content) | Inline::Link { content, .. } => {
+                render_inlines(content)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,170 @@
+use super::test_runner::{CaseResult, TestReport, Verdict};
+use clap::ValueEnum;
+use similar::TextDiff;
+use std::fmt::Write;
+
+/// Test report formats for other tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// JUnit XML, read by most CI systems
+    Junit,
+    /// Test Anything Protocol
+    Tap,
+}
+
+impl ReportFormat {
+    /// Render test results, one test suite per problem
+    pub fn render(&self, reports: &[TestReport]) -> String {
+        match self {
+            Self::Junit => render_junit(reports),
+            Self::Tap => render_tap(reports),
+        }
+    }
+}
+
+/// Short description of why a case failed, with a diff for wrong answers
+fn failure_message(case: &CaseResult) -> (&'static str, String) {
+    match case.verdict {
+        Verdict::Passed => ("Passed", String::new()),
+        Verdict::WrongAnswer => (
+            "Wrong answer",
+            TextDiff::from_lines(case.expected.trim(), case.output.trim())
+                .unified_diff()
+                .missing_newline_hint(false)
+                .header("expected", "actual")
+                .to_string(),
+        ),
+        Verdict::TimedOut => ("Timed out", String::new()),
+        Verdict::RuntimeError => (
+            "Runtime error",
+            format!("exited with {}", case.exit_status.as_deref().unwrap_or("?")),
+        ),
+    }
+}
+
+fn verdict_name(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Passed => "passed",
+        Verdict::WrongAnswer => "wrong_answer",
+        Verdict::TimedOut => "timed_out",
+        Verdict::RuntimeError => "runtime_error",
+    }
+}
+
+fn render_junit(reports: &[TestReport]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    for report in reports {
+        if let Some(error) = report.error {
+            let _ = writeln!(
+                out,
+                r#"  <testsuite name="problem {0}" tests="1" failures="0" errors="1" time="0.000">
+    <testcase name="build" classname="usaco.problem{0}" time="0.000">
+      <error message="Could not test solution">{1}</error>
+    </testcase>
+  </testsuite>"#,
+                report.problem_id,
+                escape_xml(error)
+            );
+            continue;
+        }
+        let time: u64 = report.cases.iter().map(|c| c.time_ms).sum();
+        let _ = writeln!(
+            out,
+            r#"  <testsuite name="problem {}" tests="{}" failures="{}" time="{:.3}">"#,
+            report.problem_id,
+            report.total,
+            report.total - report.passed,
+            time as f64 / 1000.0
+        );
+        for case in report.cases {
+            let _ = write!(
+                out,
+                r#"    <testcase name="case {}" classname="usaco.problem{}" time="{:.3}""#,
+                case.case,
+                report.problem_id,
+                case.time_ms as f64 / 1000.0
+            );
+            if case.verdict == Verdict::Passed {
+                out.push_str("/>\n");
+                continue;
+            }
+            let (message, details) = failure_message(case);
+            let _ = writeln!(
+                out,
+                r#">
+      <failure type="{}" message="{}">{}</failure>
+    </testcase>"#,
+                verdict_name(case.verdict),
+                message,
+                escape_xml(&details)
+            );
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn render_tap(reports: &[TestReport]) -> String {
+    // solutions that couldn't be tested count as one failed test
+    let total: usize = reports
+        .iter()
+        .map(|r| if r.error.is_some() { 1 } else { r.total })
+        .sum();
+    let mut out = format!("TAP version 13\n1..{}\n", total);
+    let mut i = 0;
+    for report in reports {
+        if let Some(error) = report.error {
+            i += 1;
+            let _ = writeln!(out, "not ok {} - problem {} build", i, report.problem_id);
+            let _ = writeln!(out, "  ---");
+            let _ = writeln!(out, "  message: Could not test solution");
+            out.push_str("  details: |\n");
+            for line in error.lines() {
+                let _ = writeln!(out, "    {}", line);
+            }
+            let _ = writeln!(out, "  ...");
+            continue;
+        }
+        for case in report.cases {
+            i += 1;
+            render_tap_case(&mut out, i, report.problem_id, case);
+        }
+    }
+    out
+}
+
+fn render_tap_case(out: &mut String, i: usize, problem_id: u64, case: &CaseResult) {
+    let ok = if case.verdict == Verdict::Passed {
+        "ok"
+    } else {
+        "not ok"
+    };
+    let _ = writeln!(
+        out,
+        "{} {} - problem {} case {}",
+        ok, i, problem_id, case.case
+    );
+    // YAML diagnostics block
+    let _ = writeln!(out, "  ---");
+    let _ = writeln!(out, "  verdict: {}", verdict_name(case.verdict));
+    let _ = writeln!(out, "  time_ms: {}", case.time_ms);
+    if case.verdict != Verdict::Passed {
+        let (message, details) = failure_message(case);
+        let _ = writeln!(out, "  message: {}", message);
+        if !details.is_empty() {
+            out.push_str("  details: |\n");
+            for line in details.lines() {
+                let _ = writeln!(out, "    {}", line);
+            }
+        }
+    }
+    let _ = writeln!(out, "  ...");
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,26 +1,40 @@
 use super::{
+    batch::{find_solutions, print_matrix, BatchEntry, BatchReport, BatchStatus},
+    compare::{print_comparison, Comparison, VariantResult},
+    diff::{first_difference, render_diff, DiffStyle},
+    output::{print_json, OutputFormat},
+    pager::{page, terminal_width},
     problem::{get_problem, open_url},
-    status_spinner::StatusSpinner,
+    render::render_document,
+    report::ReportFormat,
+    status_spinner::{last_failure, take_last_failure, StatusSpinner},
+    test_runner::{save_failures, CaseResult, CaseSet, TestReport, TestRunner, Verdict},
     CliError,
 };
 use crate::{
-    http_client::{Division, HttpClient, IoMode, Problem},
+    http_client::{parse_official_test_cases, Division, HttpClient, IoMode, Problem, TestCase},
     preferences::{CPPCompiler, DataStore, Language, Preferences},
 };
-use clap::{ArgAction, Subcommand};
-use console::{style, Style};
+use clap::{builder::PossibleValuesParser, ArgAction, Subcommand};
+use console::{style, user_attended};
 use dialoguer::{Select, theme::ColorfulTheme};
 use directories::ProjectDirs;
 use indicatif::MultiProgress;
 use log::{error, info, warn};
-use similar::{ChangeTag, TextDiff};
-use std::{borrow::Cow, io::ErrorKind, path::{Path, PathBuf}, process::Stdio, time::Duration, ops::Deref};
+use std::{
+    borrow::Cow,
+    io::ErrorKind,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process::Stdio,
+    slice,
+    time::Duration,
+};
 use tokio::{
-    fs::{create_dir_all, metadata, read_to_string, remove_file, try_exists, write, remove_dir_all},
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    fs::{create_dir_all, metadata, read_to_string, remove_dir_all, try_exists, write},
+    io::{AsyncBufReadExt, BufReader},
     process::Command as ProcessCommand,
     select,
-    time::timeout,
 };
 
 #[derive(Subcommand, Debug)]
@@ -37,16 +51,34 @@ pub enum Command {
     Create {
         /// Problem ID. Will prompt if not given and if current problem is not set.
         problem_id: Option<u64>,
+        /// Solution variant, like "brute" for `<id>.brute.cpp`
+        #[arg(short = 'V', long, value_parser = parse_variant)]
+        variant: Option<String>,
     },
     /// Compile a solution without testing
     Compile {
         /// Problem ID. Will prompt if not given and if current problem is not set.
         problem_id: Option<u64>,
+        /// Solution variant, like "brute" for `<id>.brute.cpp`
+        #[arg(short = 'V', long, value_parser = parse_variant)]
+        variant: Option<String>,
     },
     /// Test a solution using sample data
     Test {
         /// Problem ID. Will prompt if not given and if current problem is not set.
         problem_id: Option<u64>,
+        /// Test every solution in the solutions directory
+        #[arg(short, long, conflicts_with = "problem_id")]
+        all: bool,
+        /// With --all, only test solutions from this division
+        #[arg(long, requires = "all", value_parser = PossibleValuesParser::new(Division::get_all()))]
+        division: Option<String>,
+        /// Solution variant, like "brute" for `<id>.brute.cpp`
+        #[arg(short = 'V', long, value_parser = parse_variant, conflicts_with = "all")]
+        variant: Option<String>,
+        /// Run every variant of the solution on the same cases and report disagreements
+        #[arg(short, long, conflicts_with_all = ["all", "variant", "report"])]
+        compare: bool,
         /// Test with official problem data. Only available for problems from past contests
         #[arg(short = 'o', long)]
         use_official_data: bool,
@@ -63,14 +95,56 @@ pub enum Command {
             default_value = "false"
         )]
         show_diffs: bool,
-        /// Apply a time limit in seconds. When used as a flag, defaults to 2 (C++) and 4 (Python)
+        /// How to show diffs
+        #[arg(long, value_enum, default_value_t)]
+        diff_style: DiffStyle,
+        /// Lines of context to show around the first mismatch in diffs
+        #[arg(long, default_value_t = 3)]
+        context: usize,
+        /// Apply a time limit in seconds. When used as a flag, defaults to the problem's limit
+        /// (usually 2 for C++), doubled for Python
         #[arg(short, long, default_missing_value = "-1", num_args = 0..=1, require_equals = true)]
         time_limit: Option<i8>,
+        /// Apply a memory limit in megabytes. When used as a flag, defaults to the problem's limit
+        /// (usually 256). Only supported on Unix
+        #[arg(short, long, default_missing_value = "-1", num_args = 0..=1, require_equals = true)]
+        memory_limit: Option<i32>,
+        /// Only run these cases, like 1,3,5-8
+        #[arg(long, value_name = "CASES")]
+        cases: Option<CaseSet>,
+        /// Only run the cases that failed the last time this solution was tested
+        #[arg(long, conflicts_with_all = ["cases", "compare"])]
+        rerun_failed: bool,
+        /// Stop after the first failing case
+        #[arg(long)]
+        fail_fast: bool,
+        /// Save the input, expected output, actual output and stderr of failing cases.
+        /// Defaults to a directory in the cache
+        #[arg(long, value_name = "DIR", num_args = 0..=1, require_equals = true)]
+        save_failures: Option<Option<PathBuf>>,
+        /// Write a test report for other tools to --report-path
+        #[arg(long, value_enum, requires = "report_path")]
+        report: Option<ReportFormat>,
+        /// Where to write the test report
+        #[arg(long, value_name = "PATH", requires = "report")]
+        report_path: Option<PathBuf>,
     },
     /// Debug a solution using an interactive debugger
     Debug {
         /// Problem ID. Will prompt if not given and if current problem is not set.
         problem_id: Option<u64>,
+        /// Solution variant, like "brute" for `<id>.brute.cpp`
+        #[arg(short = 'V', long, value_parser = parse_variant)]
+        variant: Option<String>,
+        /// Case to use as input. Will prompt if not given and the problem has more than one
+        #[arg(long)]
+        case: Option<usize>,
+        /// Choose from the official problem data instead of the samples
+        #[arg(short = 'o', long)]
+        use_official_data: bool,
+        /// Use a custom input file instead of a test case
+        #[arg(short, long, conflicts_with_all = ["case", "use_official_data"])]
+        input: Option<PathBuf>,
     },
     /// View the official solution writeup. Only available for problems from past contests
     Writeup {
@@ -79,6 +153,9 @@ pub enum Command {
         /// Open the writeup in the default browser
         #[arg(short, long)]
         open: bool,
+        /// Refetch the problem even if it is cached
+        #[arg(short, long)]
+        refresh: bool,
     },
 }
 
@@ -109,6 +186,85 @@ pub async fn file_newer<T: AsRef<Path>>(file1: T, file2: T) -> std::io::Result<b
     Ok(file2_modified > file1_modified)
 }
 
+/// log the outcome of a test case
+fn print_case_result(result: &CaseResult, options: &TestOptions, memory_limit: Option<u64>) {
+    match result.verdict {
+        Verdict::Passed => info!(
+            "Case {} passed {}",
+            result.case,
+            style(format!("({}ms)", result.time_ms)).dim()
+        ),
+        Verdict::TimedOut => error!("Case {} timed out", result.case),
+        // allocations fail once the memory limit is hit, which usually crashes
+        Verdict::RuntimeError => match memory_limit {
+            Some(memory_limit) => error!(
+                "Case {} failed: exited with {}, possibly from exceeding the {} MB memory limit",
+                result.case,
+                result.exit_status.as_deref().unwrap_or("?"),
+                memory_limit
+            ),
+            None => error!(
+                "Case {} failed: exited with {}",
+                result.case,
+                result.exit_status.as_deref().unwrap_or("?")
+            ),
+        },
+        Verdict::WrongAnswer => {
+            let summary = match first_difference(&result.expected, &result.output) {
+                Some(mismatch) => mismatch.to_string(),
+                None => "output differs only in whitespace".into(),
+            };
+            if options.show_diffs {
+                error!(
+                    "Case {} failed: {}\n{}",
+                    result.case,
+                    summary,
+                    style("Diff:").cyan()
+                );
+                print_diff(result.expected.trim(), result.output.trim(), options);
+            } else {
+                error!("Case {} failed: {}", result.case, summary);
+            }
+        }
+    }
+}
+
+/// log how the answers of variants differ from the first variant's
+fn print_disagreements(comparison: &Comparison, options: &TestOptions) {
+    for &case in &comparison.disagreements {
+        let mut answers = comparison
+            .variants
+            .iter()
+            .filter_map(|v| Some((&v.file, v.answer(case)?)));
+        let Some((first, first_answer)) = answers.next() else {
+            continue;
+        };
+        for (variant, answer) in answers.filter(|(_, answer)| *answer != first_answer) {
+            info!(
+                "{}",
+                style(format!("Case {}: {} vs {}", case, first, variant)).cyan()
+            );
+            print_diff(first_answer, answer, options);
+        }
+    }
+}
+
+/// message for a solution that could not be tested
+fn failure_message(err: &CliError) -> String {
+    match err {
+        // the message was shown by a spinner
+        CliError::ExitError => last_failure().unwrap_or_else(|| "Failed".into()),
+        err => err.to_string(),
+    }
+}
+
+/// log a diff around the first mismatch
+fn print_diff(expected: &str, actual: &str, options: &TestOptions) {
+    for line in render_diff(expected, actual, options.diff_style, options.context) {
+        info!("{}", line);
+    }
+}
+
 /// windows uses py/py3
 fn get_python_executable() -> std::io::Result<Option<&'static str>> {
     for name in ["python3", "python", "py3", "py"] {
@@ -132,25 +288,56 @@ fn get_python_executable() -> std::io::Result<Option<&'static str>> {
     Ok(None)
 }
 
-/// compile solution and return output run file
-async fn compile_solution<T: Deref<Target = Preferences>>(problem: &Problem, multi: &MultiProgress, prefs: T) -> super::Result<PathBuf> {
-    let filename = format!("{}.{}", problem.id, prefs.preferred_language.to_str());
+/// variant names end up in file names, so keep them simple
+fn parse_variant(s: &str) -> Result<String, String> {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(s.into())
+    } else {
+        Err("variants may only contain letters, numbers, '-' and '_'".into())
+    }
+}
+
+/// location of a problem's solution file: `<id>.<ext>`, or `<id>.<variant>.<ext>`
+fn solution_file(
+    prefs: &Preferences,
+    problem: &Problem,
+    lang: Language,
+    variant: Option<&str>,
+) -> PathBuf {
     let dir = prefs.solutions_dir.as_ref().unwrap();
-    let problem_file = dir
-        .join("src")
+    let filename = match variant {
+        Some(variant) => format!("{}.{}.{}", problem.id, variant, lang.to_str()),
+        None => format!("{}.{}", problem.id, lang.to_str()),
+    };
+    dir.join("src")
         .join(problem.division.to_str())
-        .join(filename);
+        .join(filename)
+}
+
+/// compile solution and return output run file
+async fn compile_solution<T: Deref<Target = Preferences>>(
+    problem: &Problem,
+    problem_file: PathBuf,
+    lang: Language,
+    multi: &MultiProgress,
+    prefs: T,
+) -> super::Result<PathBuf> {
+    let dir = prefs.solutions_dir.as_ref().unwrap();
     // problem file for python, out file for cpp
     let mut run_file = problem_file.clone();
     
     if try_exists(&problem_file).await? {
         // compile
         let status = StatusSpinner::new("Compiling solution...", &multi);
-        if prefs.preferred_language == Language::CPP {            
+        if lang == Language::CPP {
             // make sure the output dir exists
             let mut out_file = dir.join("bin").join(problem.division.to_str());
             create_dir_all(&out_file).await?;
-            out_file.push(problem.id.to_string());
+            // named after the source file so variants don't overwrite each other
+            out_file.push(problem_file.file_stem().unwrap());
             
             // if run file is newer than source file, no compilation needed
             if file_newer(&problem_file, &out_file).await? {
@@ -210,12 +397,236 @@ async fn compile_solution<T: Deref<Target = Preferences>>(problem: &Problem, mul
     }
 }
 
+/// Failing cases are saved here in the cache dir by default
+const FAILURES_DIR_NAME: &str = "failures";
+
+/// Runs a Python solution under pdb with stdin read from a file,
+/// while pdb itself keeps reading commands from the terminal
+const PDB_WRAPPER: &str = r#"import pdb
+import sys
+
+input_file, path = sys.argv[1:3]
+sys.argv = sys.argv[2:]
+with open(path) as f:
+    code = compile(f.read(), path, "exec")
+sys.stdin = open(input_file)
+debugger = pdb.Pdb(stdin=sys.__stdin__, stdout=sys.__stdout__)
+debugger.run(code, {"__name__": "__main__", "__file__": path})
+"#;
+const PDB_WRAPPER_NAME: &str = "pdb_stdin.py";
+
+/// Settings for testing solutions
+struct TestOptions {
+    use_official_data: bool,
+    show_diffs: bool,
+    diff_style: DiffStyle,
+    /// lines of context around the first mismatch
+    context: usize,
+    /// seconds, -1 for the problem's limit
+    time_limit: Option<i8>,
+    /// megabytes, -1 for the problem's limit
+    memory_limit: Option<i32>,
+    /// log each case as it finishes
+    print_cases: bool,
+    /// only run these cases
+    cases: Option<CaseSet>,
+    /// only run the cases that failed last time
+    rerun_failed: bool,
+    /// stop after the first failing case
+    fail_fast: bool,
+    /// save failing cases into a subdirectory per solution, which is cleared first
+    save_failures: Option<PathBuf>,
+}
+
+/// The problem's sample cases, or its official test data (downloaded if it isn't stored yet)
+async fn load_test_cases(
+    problem: &Problem,
+    official: bool,
+    client: &HttpClient,
+    store: &DataStore,
+    multi: &MultiProgress,
+) -> super::Result<Vec<TestCase>> {
+    Ok(if official {
+        let status = StatusSpinner::new("Downloading official test data...", multi);
+        // make sure official data has been released
+        if let Some(rd) = &problem.released_data {
+            let data = match store.get_test_data(problem.id).await? {
+                Some(data) => data,
+                None => {
+                    let data = client
+                        .download_official_test_data(&rd.official_test_case_url)
+                        .await?
+                        .to_vec();
+                    store.save_test_data(problem.id, &data).await?;
+                    data
+                }
+            };
+            let data = parse_official_test_cases(&data)?;
+            status.finish("Downloaded", true);
+            data
+        } else {
+            status.finish("Official test data has not yet been released.", false);
+            return Err(CliError::ExitError);
+        }
+    } else {
+        problem.test_cases.clone()
+    })
+}
+
+/// compile a solution and run it against the problem's test cases
+#[allow(clippy::too_many_arguments)]
+async fn test_solution<T: Deref<Target = Preferences>>(
+    problem: &Problem,
+    problem_file: PathBuf,
+    lang: Language,
+    options: &TestOptions,
+    client: &HttpClient,
+    store: &DataStore,
+    multi: &MultiProgress,
+    prefs: T,
+    cache_dir: &Path,
+) -> super::Result<Vec<CaseResult>> {
+    // `<id>` or `<id>.<variant>`
+    let name = problem_file.file_stem().unwrap().to_owned();
+    let run_file = compile_solution(problem, problem_file, lang, multi, prefs).await?;
+
+    // -1 means use the problem's limits
+    let time_limit = options.time_limit.map(|time_limit| {
+        if time_limit == -1 {
+            match lang {
+                Language::CPP => problem.time_limit(),
+                Language::Python => problem.time_limit() * 2,
+            }
+        } else {
+            Duration::from_secs(time_limit.try_into().unwrap_or(2))
+        }
+    });
+    let memory_limit = options.memory_limit.map(|memory_limit| {
+        memory_limit
+            .try_into()
+            .unwrap_or_else(|_| problem.memory_limit_mb())
+    });
+    if memory_limit.is_some() && !cfg!(unix) {
+        warn!("Memory limits are only supported on Unix");
+    }
+
+    let test_cases =
+        load_test_cases(problem, options.use_official_data, client, store, multi).await?;
+
+    // test solution
+    let status = StatusSpinner::new("Testing solution...", multi);
+    // figure out what python executable to use
+    let (program, args) = match lang {
+        Language::CPP => (run_file.into_os_string(), vec![]),
+        Language::Python => match get_python_executable()? {
+            Some(exec) => (exec.into(), vec![run_file.into_os_string()]),
+            None => {
+                status.finish("Could not find Python executable", false);
+                return Err(CliError::ExitError);
+            }
+        },
+    };
+    let mut runner = TestRunner::new(program, args, problem, cache_dir);
+    runner.time_limit = time_limit;
+    runner.memory_limit = memory_limit;
+
+    // failed cases are remembered per solution and data set
+    let failed_key = format!(
+        "{}:{}",
+        name.to_string_lossy(),
+        if options.use_official_data {
+            "official"
+        } else {
+            "samples"
+        }
+    );
+    let last_failed = if options.rerun_failed {
+        match store.get_failed_cases(&failed_key).await? {
+            Some(failed) if !failed.is_empty() => Some(failed),
+            _ => {
+                info!("No cases failed last time; running every case");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let selected = |case: usize| match (&options.cases, &last_failed) {
+        (Some(cases), _) => cases.contains(case),
+        (None, Some(failed)) => failed.contains(&case),
+        (None, None) => true,
+    };
+
+    let mut results = vec![];
+    for (i, test_case) in test_cases.iter().enumerate() {
+        if !selected(i + 1) {
+            continue;
+        }
+        let result = runner.run(i + 1, test_case).await?;
+        if options.print_cases {
+            print_case_result(&result, options, memory_limit);
+        }
+        let failed = result.verdict != Verdict::Passed;
+        results.push(result);
+        if failed && options.fail_fast {
+            break;
+        }
+    }
+    if results.is_empty() {
+        warn!(
+            "No cases selected. Cases are numbered 1 to {}",
+            test_cases.len()
+        );
+    }
+
+    // cases that weren't run keep their old status
+    let mut failed = store
+        .get_failed_cases(&failed_key)
+        .await?
+        .unwrap_or_default();
+    failed.retain(|&case| case <= test_cases.len() && !results.iter().any(|r| r.case == case));
+    failed.extend(
+        results
+            .iter()
+            .filter(|r| r.verdict != Verdict::Passed)
+            .map(|r| r.case),
+    );
+    failed.sort_unstable();
+    store.set_failed_cases(&failed_key, failed).await?;
+
+    // clean up
+    runner.clean_up().await?;
+
+    status.finish("Finished testing", true);
+
+    if let Some(dir) = &options.save_failures {
+        let dir = dir.join(name);
+        // don't mix in failures from an old run
+        match remove_dir_all(&dir).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        let saved = save_failures(&dir, &test_cases, &results).await?;
+        if saved > 0 {
+            info!(
+                "Saved {} failing case{} to {}",
+                saved,
+                if saved == 1 { "" } else { "s" },
+                dir.display()
+            );
+        }
+    }
+    Ok(results)
+}
+
 pub async fn handle(
     command: Command,
     client: HttpClient,
     store: &DataStore,
     multi: MultiProgress,
     dirs: ProjectDirs,
+    pager: bool,
+    format: OutputFormat,
 ) -> super::Result {
     let lock = store.read()?;
     if let Some(dir) = &lock.solutions_dir {
@@ -256,43 +667,65 @@ pub async fn handle(
                 remove_dir_all(bin_dir).await?;
                 status.finish("Compiled solutions removed", true);
             }
-            Command::Writeup { problem_id, open } => {
-                get_problem(problem_id, &client, store, &multi, |problem| async move {
-                    if let Some(rd) = &problem.released_data {
-                        if open {
-                            open_url(&rd.writeup_url)?;
+            Command::Writeup {
+                problem_id,
+                open,
+                refresh,
+            } => {
+                get_problem(
+                    problem_id,
+                    &client,
+                    store,
+                    &multi,
+                    refresh,
+                    format,
+                    |problem| async move {
+                        if let Some(rd) = &problem.released_data {
+                            if open {
+                                open_url(&rd.writeup_url)?;
+                            } else {
+                                let output = render_document(&rd.writeup, terminal_width());
+                                page(&format!("{}\n", output), pager).await?;
+                            }
                         } else {
-                            println!("{}", rd.writeup);
+                            // usually because the competition window is not over
+                            error!("The writeup for this problem has not yet been released");
                         }
-                    } else {
-                        // usually because the competition window is not over
-                        error!("The writeup for this problem has not yet been released");
-                    }
 
-                    Ok(())
-                })
+                        Ok(())
+                    },
+                )
                 .await?;
             }
-            Command::Create { problem_id } => {
+            Command::Create {
+                problem_id,
+                variant,
+            } => {
                 let lang = lock.preferred_language;
-                get_problem(problem_id, &client, store, &multi, |problem| async move {
-                    let filename = format!("{}.{}", problem.id, lang.to_str());
-                    let mut problem_dir = dir.join("src").join(problem.division.to_str());
-                    // make sure dir exists
-                    create_dir_all(&problem_dir).await?;
-                    problem_dir.push(filename);
-                    if try_exists(&problem_dir).await? {
-                        println!(
-                            "{} {} {}",
-                            style("Solution file").yellow(),
-                            style(problem_dir.display()).magenta().bold(),
-                            style("already exists; skipping").yellow()
-                        );
-                    } else {
-                        let code = match lang {
-                            Language::CPP => {
-                                format!(
-                                    r##"#include <bits/stdc++.h>
+                let prefs = &*lock;
+                get_problem(
+                    problem_id,
+                    &client,
+                    store,
+                    &multi,
+                    false,
+                    format,
+                    |problem| async move {
+                        let problem_dir = solution_file(prefs, &problem, lang, variant.as_deref());
+                        // make sure dir exists
+                        create_dir_all(problem_dir.parent().unwrap()).await?;
+                        if try_exists(&problem_dir).await? {
+                            println!(
+                                "{} {} {}",
+                                style("Solution file").yellow(),
+                                style(problem_dir.display()).magenta().bold(),
+                                style("already exists; skipping").yellow()
+                            );
+                        } else {
+                            let code = match lang {
+                                Language::CPP => {
+                                    format!(
+                                        r##"#include <bits/stdc++.h>
 using namespace std;
 
 int main() {{
@@ -302,76 +735,90 @@ int main() {{
   
   return 0;
 }}"##,
-                                    match problem.input {
-                                        IoMode::Stdio => Cow::Borrowed(""),
-                                        IoMode::File(filename) => Cow::Owned(format!(
-                                            r#"  freopen("{}", "r", stdin);
+                                        match problem.input {
+                                            IoMode::Stdio => Cow::Borrowed(""),
+                                            IoMode::File(filename) => Cow::Owned(format!(
+                                                r#"  freopen("{}", "r", stdin);
 "#,
-                                            filename
-                                        )),
-                                    },
-                                    match problem.output {
-                                        IoMode::Stdio => Cow::Borrowed(""),
-                                        IoMode::File(filename) => Cow::Owned(format!(
-                                            r#"  freopen("{}", "w", stdout);
+                                                filename
+                                            )),
+                                        },
+                                        match problem.output {
+                                            IoMode::Stdio => Cow::Borrowed(""),
+                                            IoMode::File(filename) => Cow::Owned(format!(
+                                                r#"  freopen("{}", "w", stdout);
 "#,
-                                            filename
-                                        )),
-                                    },
-                                )
-                            }
-                            Language::Python => {
-                                format!(
-                                    r#"import sys
+                                                filename
+                                            )),
+                                        },
+                                    )
+                                }
+                                Language::Python => {
+                                    format!(
+                                        r#"import sys
 
 {}{}
 
 "#,
-                                    match problem.input {
-                                        IoMode::Stdio => Cow::Borrowed(""),
-                                        IoMode::File(filename) => Cow::Owned(format!(
-                                            r#"sys.stdin = open("{}", "r")
+                                        match problem.input {
+                                            IoMode::Stdio => Cow::Borrowed(""),
+                                            IoMode::File(filename) => Cow::Owned(format!(
+                                                r#"sys.stdin = open("{}", "r")
 "#,
-                                            filename
-                                        )),
-                                    },
-                                    match problem.output {
-                                        IoMode::Stdio => Cow::Borrowed(""),
-                                        IoMode::File(filename) => Cow::Owned(format!(
-                                            r#"sys.stdout = open("{}", "w")
+                                                filename
+                                            )),
+                                        },
+                                        match problem.output {
+                                            IoMode::Stdio => Cow::Borrowed(""),
+                                            IoMode::File(filename) => Cow::Owned(format!(
+                                                r#"sys.stdout = open("{}", "w")
 "#,
-                                            filename
-                                        )),
-                                    },
-                                )
-                            }
-                        };
-                        write(&problem_dir, &code).await?;
-                        println!(
-                            "{} {} {} {}",
-                            style("Successfully bootstrapped").green(),
-                            style(format!("problem {}", problem.id)).bold().cyan(),
-                            style("at").green(),
-                            style(problem_dir.display()).yellow().bold(),
-                        );
-                    }
-                    Ok(())
-                })
+                                                filename
+                                            )),
+                                        },
+                                    )
+                                }
+                            };
+                            write(&problem_dir, &code).await?;
+                            println!(
+                                "{} {} {} {}",
+                                style("Successfully bootstrapped").green(),
+                                style(format!("problem {}", problem.id)).bold().cyan(),
+                                style("at").green(),
+                                style(problem_dir.display()).yellow().bold(),
+                            );
+                        }
+                        Ok(())
+                    },
+                )
                 .await?;
             }
-            Command::Compile { problem_id } => {
+            Command::Compile {
+                problem_id,
+                variant,
+            } => {
                 get_problem(
                     problem_id,
                     &client.clone(),
                     store,
                     &multi.clone(),
+                    false,
+                    format,
                     |problem| async move {
-                        compile_solution(&problem, &multi, &*lock).await?;
+                        let lang = lock.preferred_language;
+                        let problem_file = solution_file(&lock, &problem, lang, variant.as_deref());
+                        compile_solution(&problem, problem_file, lang, &multi, &*lock).await?;
                         Ok(())
                     }
                 ).await?;
             }
-            Command::Debug { problem_id } => {
+            Command::Debug {
+                problem_id,
+                variant,
+                case,
+                use_official_data,
+                input,
+            } => {
                 let lang = lock.preferred_language;
                 let cache_dir = dirs.cache_dir();
                 get_problem(
@@ -379,45 +826,71 @@ int main() {{
                     &client.clone(),
                     store,
                     &multi.clone(),
+                    false,
+                    format,
                     |problem| async move {
-                        let run_file = compile_solution(&problem, &multi, &*lock).await?;
+                        let problem_file = solution_file(&lock, &problem, lang, variant.as_deref());
+                        let run_file = compile_solution(&problem, problem_file, lang, &multi, &*lock).await?;
+
+                        // pick the input: a custom file or one of the test cases
+                        let (input, input_name) = if let Some(input) = input {
+                            (read_to_string(&input).await?, "input.txt".to_string())
+                        } else {
+                            let test_cases = load_test_cases(&problem, use_official_data, &client, store, &multi).await?;
+                            let selection = match case {
+                                Some(case) if (1..=test_cases.len()).contains(&case) => case - 1,
+                                Some(case) => {
+                                    error!("There is no case {}. Cases are numbered 1 to {}", case, test_cases.len());
+                                    return Err(CliError::ExitError);
+                                }
+                                None if test_cases.len() == 1 => 0,
+                                None if !user_attended() => {
+                                    error!("Pass --case to choose a test case when not running interactively");
+                                    return Err(CliError::ExitError);
+                                }
+                                None => {
+                                    // prompt user to select a test case to use (we can only do one)
+                                    let items = 1..=test_cases.len();
+                                    let selection = Select::with_theme(&ColorfulTheme::default())
+                                        .with_prompt("Select a test case to use:")
+                                        .items(&items.collect::<Vec<_>>())
+                                        .interact_opt()?;
+                                    // cancelled with Esc or q
+                                    let Some(selection) = selection else {
+                                        return Ok(());
+                                    };
+                                    selection
+                                }
+                            };
+                            (test_cases[selection].input.clone(), format!("input{}.txt", selection + 1))
+                        };
 
                         let problem_cache_dir = cache_dir.join(format!("{}-debug", problem.id));
                         create_dir_all(&problem_cache_dir).await?;
 
-                        // save input files in the cache dir
-                        if let IoMode::File(filename) = &problem.input {
-                            // prompt user to select a test case to store (we can only do one)
-                            let items = 1..=problem.test_cases.len();
-                            let selection = Select::with_theme(&ColorfulTheme::default())
-                                .with_prompt("Select a test case to use:")
-                                .items(&items.collect::<Vec<_>>())
-                                .interact()
-                                .unwrap();
-                            write(
-                                problem_cache_dir.join(&filename),
-                                problem.test_cases[selection].input.as_bytes()
-                            ).await?;
-                        } else {
-                            let status = StatusSpinner::new("Storing input files...", &multi);
-                            // just save all files
-                            let mut filenames: Vec<Option<String>> = vec![None; problem.test_cases.len()];
-                            for (i, case) in problem.test_cases.iter().enumerate() {
-                                let filename = format!("input{}.txt", i + 1);
-                                write(problem_cache_dir.join(&filename), case.input.as_bytes()).await?;
-                                filenames[i] = Some(filename);
+                        // save the input in the cache dir
+                        let stdin_file = match &problem.input {
+                            IoMode::File(filename) => {
+                                write(problem_cache_dir.join(filename), input.as_bytes()).await?;
+                                None
                             }
+                            IoMode::Stdio => {
+                                write(problem_cache_dir.join(&input_name), input.as_bytes()).await?;
+                                info!("Input saved as {}", input_name);
+                                Some(input_name)
+                            }
+                        };
 
-                            let filenames = filenames.into_iter()
-                                .map(|f| f.unwrap())
-                                .collect::<Vec<_>>()
-                                .join(", ");
-                            status.finish(&format!("Input files saved as {}", filenames), true);
-                        }
-
-                        // start debugger
+                        // start debugger, with the input redirected to stdin for stdio problems
                         let mut command = match lang {
-                            Language::CPP => ProcessCommand::new("gdb"),
+                            Language::CPP => {
+                                let mut c = ProcessCommand::new("gdb");
+                                if let Some(stdin_file) = &stdin_file {
+                                    c.arg("-ex").arg(format!("run < {}", stdin_file));
+                                }
+                                c.arg(&run_file);
+                                c
+                            }
                             Language::Python => {
                                 let mut c = ProcessCommand::new(
                                     // figure out what python executable to use
@@ -428,209 +901,247 @@ int main() {{
                                         return Err(CliError::ExitError);
                                     }
                                 );
-                                c.arg("-m").arg("pdb");
+                                match &stdin_file {
+                                    // pdb reads commands from stdin, so the input is swapped in by a wrapper
+                                    Some(stdin_file) => {
+                                        write(problem_cache_dir.join(PDB_WRAPPER_NAME), PDB_WRAPPER).await?;
+                                        c.arg(PDB_WRAPPER_NAME).arg(stdin_file).arg(&run_file);
+                                    }
+                                    None => {
+                                        c.arg("-m").arg("pdb").arg(&run_file);
+                                    }
+                                }
                                 c
                             }
                         };
-                        
+
                         // spawn the debugger
                         command
-                            .arg(&run_file)
                             .current_dir(&problem_cache_dir)
                             .status().await?;
 
                         // clean up
                         remove_dir_all(problem_cache_dir).await?;
-                        
+
                         Ok(())
                     }
                 ).await?;
             }
             Command::Test {
                 problem_id,
+                all,
+                division,
+                variant,
+                compare,
                 use_official_data,
                 show_diffs,
+                diff_style,
+                context,
                 time_limit,
+                memory_limit,
+                cases,
+                rerun_failed,
+                fail_fast,
+                save_failures,
+                report,
+                report_path,
             } => {
-                let lang = lock.preferred_language;
+                let report = report.zip(report_path);
+                let options = TestOptions {
+                    use_official_data,
+                    show_diffs,
+                    diff_style,
+                    context,
+                    time_limit,
+                    memory_limit,
+                    print_cases: format == OutputFormat::Text && !all && !compare,
+                    cases,
+                    rerun_failed,
+                    fail_fast,
+                    // the default location is shared by every problem
+                    save_failures: save_failures
+                        .map(|dir| dir.unwrap_or_else(|| dirs.cache_dir().join(FAILURES_DIR_NAME))),
+                };
                 let cache_dir = dirs.cache_dir();
-                get_problem(
-                    problem_id,
-                    &client.clone(),
-                    store,
-                    &multi.clone(),
-                    |problem| async move {
-                        let run_file = compile_solution(&problem, &multi, &*lock).await?;
-
-                        let test_cases = if use_official_data {
-                            let status =
-                                StatusSpinner::new("Downloading official test data...", &multi);
-                            // make sure official data has been released
-                            if let Some(rd) = problem.released_data {
-                                let data = client
-                                    .get_official_test_cases(&rd.official_test_case_url)
-                                    .await?;
-                                status.finish("Downloaded", true);
-                                data
-                            } else {
-                                status.finish(
-                                    "Official test data has not yet been released.",
-                                    false,
-                                );
-                                return Err(CliError::ExitError);
-                            }
-                        } else {
-                            problem.test_cases
-                        };
-                        
-                        // test solution
-                        let status = StatusSpinner::new("Testing solution...", &multi);
-                        let in_file_name = if let IoMode::File(filename) = &problem.input {
-                            Some(cache_dir.join(filename))
-                        } else {
-                            None
-                        };
-                        let out_file_name = if let IoMode::File(filename) = &problem.output {
-                            Some(cache_dir.join(filename))
-                        } else {
-                            None
-                        };
-                        // figure out what python executable to use
-                        let python_exec = if lang == Language::Python {
-                            if let Some(exec) = get_python_executable()? {
-                                Some(exec)
-                            } else {
-                                status.finish("Could not find Python executable", false);
-                                return Err(CliError::ExitError);
-                            }
-                        } else {
-                            None
-                        };
 
-                        for (i, test_case) in test_cases.iter().enumerate() {
-                            // write input file
-                            if let Some(in_file_name) = &in_file_name {
-                                write(in_file_name, &test_case.input).await?;
-                            }
+                if all {
+                    let division = division.as_deref().and_then(Division::from_str);
+                    let solutions = find_solutions(dir, division).await?;
+                    if solutions.is_empty() {
+                        warn!("No solutions found in {}", dir.join("src").display());
+                    }
 
-                            let mut command = match lang {
-                                Language::CPP => ProcessCommand::new(&run_file),
-                                Language::Python => {
-                                    let mut c = ProcessCommand::new(python_exec.unwrap());
-                                    c.arg(&run_file);
-                                    c
-                                }
-                            };
+                    let mut entries = vec![];
+                    for solution in &solutions {
+                        info!("Testing {}", solution.path.display());
+                        // don't report an old failure for this solution
+                        take_last_failure();
+                        let mut results = Ok(vec![]);
+                        let (results_ref, options, client, multi, prefs) =
+                            (&mut results, &options, &client, &multi, &*lock);
+                        let loaded = get_problem(
+                            Some(solution.problem_id),
+                            client,
+                            store,
+                            multi,
+                            false,
+                            format,
+                            |problem| async move {
+                                *results_ref = test_solution(
+                                    &problem,
+                                    solution.path.clone(),
+                                    solution.lang,
+                                    options,
+                                    client,
+                                    store,
+                                    multi,
+                                    prefs,
+                                    cache_dir,
+                                )
+                                .await;
+                                Ok(())
+                            },
+                        )
+                        .await;
+                        let result = loaded.and(results).map_err(|e| failure_message(&e));
+                        entries.push(BatchEntry::new(solution, result));
+                    }
 
-                            // spawn the process for each test case
-                            let mut child = command
-                                .stdin(Stdio::piped())
-                                .stderr(Stdio::piped())
-                                .stdout(Stdio::piped())
-                                .current_dir(&cache_dir)
-                                .spawn()?;
-
-                            // write test case to stdin
-                            if problem.input == IoMode::Stdio {
-                                let mut stdin = child.stdin.take().unwrap();
-                                stdin.write_all(&test_case.input.as_bytes()).await?;
-                                stdin.flush().await?;
-                            }
+                    if let Some((report_format, path)) = report {
+                        let reports = entries
+                            .iter()
+                            .map(|entry| entry.report(use_official_data))
+                            .collect::<Vec<_>>();
+                        write(&path, report_format.render(&reports)).await?;
+                        info!("Wrote report to {}", path.display());
+                    }
+                    match format {
+                        OutputFormat::Json => print_json(&BatchReport::new(&entries))?,
+                        OutputFormat::Text => print_matrix(&entries),
+                    }
+                    if entries
+                        .iter()
+                        .any(|entry| entry.status != BatchStatus::Passed)
+                    {
+                        return Err(CliError::TestsFailed);
+                    }
+                    return Ok(());
+                }
 
-                            let stderr = child.stderr.take().unwrap();
+                if compare {
+                    let (options, client, multi, prefs) = (&options, &client, &multi, &*lock);
+                    get_problem(
+                        problem_id,
+                        client,
+                        store,
+                        multi,
+                        false,
+                        format,
+                        |problem| async move {
+                            let dir = prefs.solutions_dir.as_ref().unwrap();
+                            let solutions = find_solutions(dir, Some(problem.division))
+                                .await?
+                                .into_iter()
+                                .filter(|s| s.problem_id == problem.id)
+                                .collect::<Vec<_>>();
+                            if solutions.is_empty() {
+                                error!("No solutions found for problem {}", problem.id);
+                                return Err(CliError::ExitError);
+                            }
 
-                            // print stderr (for debugging)
-                            tokio::spawn(async move {
-                                let mut stderr = BufReader::new(stderr).lines();
-                                loop {
-                                    select! {
-                                        Ok(Some(line)) = stderr.next_line() => {
-                                            warn!("Run {}: {}", i + 1, line);
-                                        },
-                                        else => { break; }
-                                    }
-                                }
-                            });
-
-                            // wait for completion, possibly with timeout
-                            let out = if let Some(mut time_limit) = time_limit {
-                                if time_limit == -1 {
-                                    // apply default timeout
-                                    time_limit = match lang {
-                                        Language::CPP => 2,
-                                        Language::Python => 4,
-                                    };
-                                }
-                                match timeout(
-                                    Duration::from_secs(time_limit.try_into().unwrap_or(2)),
-                                    child.wait_with_output(),
+                            let mut variants = vec![];
+                            for solution in &solutions {
+                                info!("Testing {}", solution.path.display());
+                                take_last_failure();
+                                let result = test_solution(
+                                    &problem,
+                                    solution.path.clone(),
+                                    solution.lang,
+                                    options,
+                                    client,
+                                    store,
+                                    multi,
+                                    prefs,
+                                    cache_dir,
                                 )
                                 .await
-                                {
-                                    Ok(r) => r?,
-                                    Err(_) => {
-                                        error!("Case {} timed out", i + 1);
-                                        continue;
+                                .map_err(|e| failure_message(&e));
+                                variants.push(VariantResult::new(solution, result));
+                            }
+
+                            let comparison = Comparison::new(problem.id, &variants);
+                            match format {
+                                OutputFormat::Json => print_json(&comparison)?,
+                                OutputFormat::Text => {
+                                    print_comparison(&comparison);
+                                    if show_diffs {
+                                        print_disagreements(&comparison, options);
                                     }
                                 }
-                            } else {
-                                child.wait_with_output().await?
-                            };
-                            // get output, either by reading output file or stdout
-                            let out = if let Some(out_file_name) = &out_file_name {
-                                Cow::Owned(read_to_string(&out_file_name).await?)
-                            } else {
-                                String::from_utf8_lossy(&out.stdout)
-                            };
-
-                            let trimmed_out = out.trim();
-                            let trimmed_target_out = test_case.output.trim();
+                            }
+                            if !comparison.disagreements.is_empty() {
+                                return Err(CliError::TestsFailed);
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await?;
+                    return Ok(());
+                }
 
-                            if trimmed_out == trimmed_target_out {
-                                info!("Case {} passed", i + 1);
-                            } else {
-                                if show_diffs {
-                                    error!("Case {} failed\n{}", i + 1, style("Diff:").cyan());
-                                    // print diff
-                                    let diff =
-                                        TextDiff::from_lines(trimmed_target_out, trimmed_out);
-                                    for change in diff.iter_all_changes() {
-                                        let (sign, s) = match change.tag() {
-                                            ChangeTag::Delete => ("-", Style::new().red()),
-                                            ChangeTag::Insert => ("+", Style::new().green()),
-                                            ChangeTag::Equal => (" ", Style::new()),
-                                        };
-                                        info!(
-                                            "{}｜ {}{}",
-                                            style(
-                                                change
-                                                    .new_index()
-                                                    .map(|s| format!("{:<3}", s + 1))
-                                                    .unwrap_or_else(|| "   ".to_string())
-                                            )
-                                            .dim(),
-                                            s.apply_to(sign).bold(),
-                                            s.apply_to(
-                                                change.as_str().unwrap_or("").trim_end()
-                                            )
-                                        );
-                                    }
-                                } else {
-                                    error!("Case {} failed", i + 1);
+                let lang = lock.preferred_language;
+                get_problem(
+                    problem_id,
+                    &client.clone(),
+                    store,
+                    &multi.clone(),
+                    false,
+                    format,
+                    |problem| async move {
+                        let results = match test_solution(
+                            &problem,
+                            solution_file(&lock, &problem, lang, variant.as_deref()),
+                            lang,
+                            &options,
+                            &client,
+                            store,
+                            &multi,
+                            &*lock,
+                            cache_dir,
+                        )
+                        .await
+                        {
+                            Ok(results) => results,
+                            Err(err) => {
+                                // still write a report, so CI sees that the solution couldn't be tested
+                                if let Some((report_format, path)) = &report {
+                                    let error = failure_message(&err);
+                                    let mut test_report =
+                                        TestReport::new(problem.id, use_official_data, &[]);
+                                    test_report.error = Some(&error);
+                                    write(
+                                        path,
+                                        report_format.render(slice::from_ref(&test_report)),
+                                    )
+                                    .await?;
+                                    info!("Wrote report to {}", path.display());
                                 }
+                                return Err(err);
                             }
-                        }
+                        };
 
-                        // clean up
-                        if let Some(in_file_name) = &in_file_name {
-                            remove_file(in_file_name).await?;
+                        let test_report = TestReport::new(problem.id, use_official_data, &results);
+                        if let Some((report_format, path)) = report {
+                            write(&path, report_format.render(slice::from_ref(&test_report)))
+                                .await?;
+                            info!("Wrote report to {}", path.display());
                         }
-                        if let Some(out_file_name) = &out_file_name {
-                            remove_file(out_file_name).await?;
+                        if format == OutputFormat::Json {
+                            print_json(&test_report)?;
+                        }
+                        if test_report.passed < test_report.total {
+                            return Err(CliError::TestsFailed);
                         }
-
-                        status.finish("Finished testing", true);
-                        
 
                         Ok(())
                     },
@@ -638,6 +1149,9 @@ int main() {{
                 .await?;
             }
         }
+    } else if format == OutputFormat::Json {
+        StatusSpinner::new("", &multi).finish("The solutions directory is not set", false);
+        return Err(CliError::ExitError);
     } else {
         // prompt user to setup solutions dir
         println!(
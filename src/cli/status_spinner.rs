@@ -1,7 +1,20 @@
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
-use std::time::Duration;
+use std::{sync::Mutex, time::Duration};
+
+/// message of the last unsuccessful `finish`, reported in JSON error output
+static LAST_FAILURE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Take the message of the last spinner that finished unsuccessfully
+pub fn take_last_failure() -> Option<String> {
+    LAST_FAILURE.lock().ok()?.take()
+}
+
+/// The message of the last spinner that finished unsuccessfully, leaving it in place
+pub fn last_failure() -> Option<String> {
+    LAST_FAILURE.lock().ok()?.clone()
+}
 
 pub struct StatusSpinner<'a> {
     multi: &'a MultiProgress,
@@ -18,6 +31,12 @@ impl<'a> StatusSpinner<'a> {
     }
 
     pub fn finish(&self, message: &str, success: bool) {
+        if !success {
+            if let Ok(mut last) = LAST_FAILURE.lock() {
+                *last = Some(message.to_string());
+            }
+        }
+
         // show the prefix
         self.bar.set_style(
             ProgressStyle::default_spinner()
@@ -0,0 +1,333 @@
+use crate::http_client::{IoMode, Problem, TestCase};
+use log::warn;
+use serde::Serialize;
+use std::{
+    ffi::OsString,
+    io::{self, ErrorKind},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs::{create_dir_all, read_to_string, remove_file, write},
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::Command,
+    time::timeout,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Passed,
+    WrongAnswer,
+    TimedOut,
+    /// exited with a non-zero status, e.g. from a crash or running out of memory
+    RuntimeError,
+}
+
+/// A set of 1-based case numbers, like `1,3,5-8`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseSet(Vec<RangeInclusive<usize>>);
+
+impl CaseSet {
+    pub fn contains(&self, case: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&case))
+    }
+}
+
+impl FromStr for CaseSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("\"{}\" is not a case number", n.trim()))
+        };
+        s.split(',')
+            .map(|part| match part.split_once('-') {
+                Some((start, end)) => {
+                    let (start, end) = (parse(start)?, parse(end)?);
+                    if start > end {
+                        return Err(format!("\"{}\" is an empty range", part.trim()));
+                    }
+                    Ok(start..=end)
+                }
+                None => parse(part).map(|n| n..=n),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// The outcome of running a solution on one test case
+#[derive(Debug, Serialize)]
+pub struct CaseResult {
+    /// 1-based case number
+    pub case: usize,
+    pub verdict: Verdict,
+    /// wall clock time, including process startup
+    pub time_ms: u64,
+    /// how the process exited, for runtime errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_status: Option<String>,
+    #[serde(skip)]
+    pub expected: String,
+    #[serde(skip)]
+    pub output: String,
+    #[serde(skip)]
+    pub stderr: String,
+}
+
+/// Results of testing a solution, as printed by `--format json`
+#[derive(Debug, Serialize)]
+pub struct TestReport<'a> {
+    pub problem_id: u64,
+    pub official_data: bool,
+    pub passed: usize,
+    pub total: usize,
+    /// why the solution couldn't be tested, e.g. a compile error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    pub cases: &'a [CaseResult],
+}
+
+impl<'a> TestReport<'a> {
+    pub fn new(problem_id: u64, official_data: bool, cases: &'a [CaseResult]) -> Self {
+        Self {
+            problem_id,
+            official_data,
+            passed: cases
+                .iter()
+                .filter(|c| c.verdict == Verdict::Passed)
+                .count(),
+            total: cases.len(),
+            error: None,
+            cases,
+        }
+    }
+}
+
+/// Runs a compiled solution against test cases
+pub struct TestRunner {
+    program: OsString,
+    args: Vec<OsString>,
+    working_dir: PathBuf,
+    input: IoMode,
+    output: IoMode,
+    pub time_limit: Option<Duration>,
+    /// in megabytes
+    pub memory_limit: Option<u64>,
+}
+
+impl TestRunner {
+    /// `program` and `args` run the solution. File I/O happens in `working_dir`
+    pub fn new(
+        program: impl Into<OsString>,
+        args: Vec<OsString>,
+        problem: &Problem,
+        working_dir: &Path,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            working_dir: working_dir.to_path_buf(),
+            input: problem.input.clone(),
+            output: problem.output.clone(),
+            time_limit: None,
+            memory_limit: None,
+        }
+    }
+
+    fn io_file(&self, mode: &IoMode) -> Option<PathBuf> {
+        match mode {
+            IoMode::File(name) => Some(self.working_dir.join(name)),
+            IoMode::Stdio => None,
+        }
+    }
+
+    /// Run a single test case. `case` is the 1-based case number
+    pub async fn run(&self, case: usize, test_case: &TestCase) -> io::Result<CaseResult> {
+        // write input file
+        if let Some(in_file) = self.io_file(&self.input) {
+            write(in_file, &test_case.input).await?;
+        }
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(memory_limit) = self.memory_limit {
+            apply_memory_limit(&mut command, memory_limit);
+        }
+
+        let start = Instant::now();
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
+            .current_dir(&self.working_dir)
+            // stop solutions that time out
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // write test case to stdin. in the background so large outputs can't deadlock
+        let mut stdin = child.stdin.take().unwrap();
+        let input = (self.input == IoMode::Stdio).then(|| test_case.input.clone());
+        tokio::spawn(async move {
+            if let Some(input) = input {
+                // the solution may exit without reading everything
+                let _ = stdin.write_all(input.as_bytes()).await;
+            }
+        });
+
+        // print stderr (for debugging) and keep it for saving failures
+        let stderr = child.stderr.take().unwrap();
+        let stderr = tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            let mut collected = String::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                warn!("Run {}: {}", case, line);
+                collected.push_str(&line);
+                collected.push('\n');
+            }
+            collected
+        });
+
+        // wait for completion, possibly with timeout
+        let out = match self.time_limit {
+            Some(time_limit) => timeout(time_limit, child.wait_with_output()).await.ok(),
+            None => Some(child.wait_with_output().await),
+        };
+        let time_ms = start.elapsed().as_millis() as u64;
+        // the process was killed on a timeout, so this finishes
+        let stderr = stderr.await.unwrap_or_default();
+
+        let mut result = CaseResult {
+            case,
+            verdict: Verdict::TimedOut,
+            time_ms,
+            exit_status: None,
+            expected: test_case.output.clone(),
+            output: String::new(),
+            stderr,
+        };
+        let Some(out) = out else {
+            return Ok(result);
+        };
+        let out = out?;
+
+        // get output, either by reading output file or stdout
+        result.output = match self.io_file(&self.output) {
+            Some(out_file) => match read_to_string(out_file).await {
+                Ok(s) => s,
+                // it may have crashed before writing anything
+                Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e),
+            },
+            None => String::from_utf8_lossy(&out.stdout).into_owned(),
+        };
+
+        result.verdict = if !out.status.success() {
+            result.exit_status = Some(describe_status(out.status));
+            Verdict::RuntimeError
+        } else if result.output.trim() == test_case.output.trim() {
+            Verdict::Passed
+        } else {
+            Verdict::WrongAnswer
+        };
+        Ok(result)
+    }
+
+    /// Remove input and output files
+    pub async fn clean_up(&self) -> io::Result<()> {
+        for file in [self.io_file(&self.input), self.io_file(&self.output)]
+            .into_iter()
+            .flatten()
+        {
+            match remove_file(file).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write the input, expected output, actual output and stderr of failing cases to `dir`
+/// as `N.in`, `N.expected`, `N.actual` and `N.stderr`. Returns how many cases were saved
+pub async fn save_failures(
+    dir: &Path,
+    test_cases: &[TestCase],
+    results: &[CaseResult],
+) -> io::Result<usize> {
+    let mut saved = 0;
+    for result in results {
+        if result.verdict == Verdict::Passed {
+            continue;
+        }
+        let test_case = &test_cases[result.case - 1];
+        if saved == 0 {
+            create_dir_all(dir).await?;
+        }
+        let n = result.case;
+        write(dir.join(format!("{}.in", n)), &test_case.input).await?;
+        write(dir.join(format!("{}.expected", n)), &test_case.output).await?;
+        write(dir.join(format!("{}.actual", n)), &result.output).await?;
+        write(dir.join(format!("{}.stderr", n)), &result.stderr).await?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+fn describe_status(status: ExitStatus) -> String {
+    status.to_string().trim_start_matches("exit ").into()
+}
+
+/// limit the address space of a test run
+#[cfg(unix)]
+fn apply_memory_limit(command: &mut Command, megabytes: u64) {
+    let bytes = (megabytes * 1024 * 1024) as libc::rlim_t;
+    // SAFETY: setrlimit is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            let limit = libc::rlimit {
+                rlim_cur: bytes,
+                rlim_max: bytes,
+            };
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn apply_memory_limit(_command: &mut Command, _megabytes: u64) {}
+
+#[cfg(test)]
+mod tests {
+    use super::CaseSet;
+
+    #[test]
+    fn parses_case_sets() {
+        let cases = "1,3,5-8".parse::<CaseSet>().unwrap();
+        for case in [1, 3, 5, 6, 8] {
+            assert!(cases.contains(case), "case {}", case);
+        }
+        for case in [2, 4, 9] {
+            assert!(!cases.contains(case), "case {}", case);
+        }
+        assert_eq!(" 2 - 2 ".parse(), Ok(CaseSet(vec![2..=2])));
+    }
+
+    #[test]
+    fn rejects_invalid_case_sets() {
+        for s in ["3-1", "0", "0-2", "x", "1,", "1-", ""] {
+            assert!(s.parse::<CaseSet>().is_err(), "parsing {:?}", s);
+        }
+    }
+}
@@ -1,19 +1,32 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    env,
+    path::PathBuf,
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
 
+use crate::atomic_write::atomic_write;
 use async_trait::async_trait;
 use directories::ProjectDirs;
-use log::debug;
+use log::{debug, warn};
 #[cfg(target_os = "linux")]
 use secret_service::{Collection, EncryptionType, Item, SecretService};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_slice, to_vec};
 use thiserror::Error;
-use tokio::fs::{create_dir_all, read, remove_file, try_exists, write};
+use tokio::{
+    fs::{read, remove_file, try_exists},
+    process::Command,
+    sync::OnceCell,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UsacoCredentials {
     pub username: String,
-    pub password: String,
+    /// not stored when using session-only login
+    #[serde(default)]
+    pub password: Option<String>,
     pub session_id: String,
 }
 
@@ -24,12 +37,20 @@ pub enum CredentialStorageError {
     SecretService(#[from] secret_service::Error),
     #[error("Password is not valid UTF-8")]
     InvalidPassword,
+    #[error(
+        "Stored credentials use an unsupported format (version {0}). Log in again to replace them"
+    )]
+    UnsupportedVersion(u64),
     #[error("Missing username in secret item")]
     MissingUsername,
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Serialization error: {0}")]
     SerdeError(#[from] serde_json::Error),
+    #[error("Credentials are read-only when provided by environment variables. Unset USACO_USERNAME to use another backend")]
+    ReadOnly,
+    #[error("Credential helper failed: {0}")]
+    HelperFailed(String),
 }
 
 type Result<T> = std::result::Result<T, CredentialStorageError>;
@@ -61,6 +82,11 @@ async fn get_secret_storage_provider() -> Option<Arc<dyn CredentialStorage>> {
 
 /// Automatically select a credential storage provider
 pub async fn autoselect_cred_storage(dirs: &ProjectDirs) -> Arc<dyn CredentialStorage> {
+    // environment variables take priority (CI, scripts)
+    if let Some(provider) = CredentialStorageEnv::from_env(dirs) {
+        return Arc::new(provider);
+    }
+
     // try secret storage
     if let Some(provider) = get_secret_storage_provider().await {
         return provider;
@@ -85,8 +111,7 @@ impl CredentialStoragePlaintext {
 #[async_trait(?Send)]
 impl CredentialStorage for CredentialStoragePlaintext {
     async fn store_credentials(&self, creds: &UsacoCredentials) -> Result<()> {
-        create_dir_all(self.filename.parent().unwrap()).await?;
-        write(&self.filename, to_vec(creds)?).await?;
+        atomic_write(&self.filename, to_vec(creds)?).await?;
         Ok(())
     }
     async fn clear_credentials(&self) -> Result<()> {
@@ -108,6 +133,163 @@ impl CredentialStorage for CredentialStoragePlaintext {
     }
 }
 
+/// Where the env provider gets the password from
+enum PasswordSource {
+    /// `USACO_PASSWORD`
+    Env(String),
+    /// `USACO_PASSWORD_COMMAND`, e.g. `pass show usaco`
+    Command(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSession {
+    username: String,
+    session_id: String,
+}
+
+/// Read-only cred storage provider using environment variables or a credential helper command
+/// Refreshed session IDs are kept in memory and in a sidecar file in the cache folder
+pub struct CredentialStorageEnv {
+    username: String,
+    password_source: PasswordSource,
+    /// helper output is only fetched once
+    password: OnceCell<String>,
+    session_id: Mutex<Option<String>>,
+    session_filename: PathBuf,
+}
+
+impl CredentialStorageEnv {
+    /// Returns None if `USACO_USERNAME` and a password source are not both set
+    pub fn from_env(dirs: &ProjectDirs) -> Option<Self> {
+        let username = env::var("USACO_USERNAME").ok()?;
+        let password_source = if let Ok(password) = env::var("USACO_PASSWORD") {
+            PasswordSource::Env(password)
+        } else {
+            PasswordSource::Command(env::var("USACO_PASSWORD_COMMAND").ok()?)
+        };
+        Some(Self {
+            username,
+            password_source,
+            password: OnceCell::new(),
+            session_id: Mutex::new(None),
+            session_filename: dirs.cache_dir().join("session.json"),
+        })
+    }
+
+    /// run the helper command and use the first line of its output
+    async fn run_helper(command: &str) -> Result<String> {
+        debug!("Running credential helper");
+        let mut shell = if cfg!(target_os = "windows") {
+            let mut c = Command::new("cmd.exe");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        let output = shell
+            .arg(command)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(CredentialStorageError::HelperFailed(format!(
+                "`{}` exited with {}",
+                command, output.status
+            )));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| CredentialStorageError::InvalidPassword)?;
+        stdout
+            .lines()
+            .next()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.to_string())
+            .ok_or_else(|| {
+                CredentialStorageError::HelperFailed(format!("`{}` printed no password", command))
+            })
+    }
+
+    async fn get_password(&self) -> Result<&String> {
+        self.password
+            .get_or_try_init(|| async {
+                match &self.password_source {
+                    PasswordSource::Env(password) => Ok(password.clone()),
+                    PasswordSource::Command(command) => Self::run_helper(command).await,
+                }
+            })
+            .await
+    }
+
+    async fn get_session_id(&self) -> Result<String> {
+        if let Some(session_id) = self.session_id.lock().unwrap().as_ref() {
+            return Ok(session_id.clone());
+        }
+        // fall back to the sidecar file
+        if try_exists(&self.session_filename).await? {
+            let cached: CachedSession = from_slice(&read(&self.session_filename).await?)?;
+            if cached.username == self.username {
+                return Ok(cached.session_id);
+            }
+        }
+        // empty session - will be refreshed on the first authed request
+        Ok(String::new())
+    }
+}
+
+#[async_trait(?Send)]
+impl CredentialStorage for CredentialStorageEnv {
+    async fn store_credentials(&self, creds: &UsacoCredentials) -> Result<()> {
+        if creds.username != self.username {
+            return Err(CredentialStorageError::ReadOnly);
+        }
+        // only the session can be updated
+        *self.session_id.lock().unwrap() = Some(creds.session_id.clone());
+        let cached = CachedSession {
+            username: creds.username.clone(),
+            session_id: creds.session_id.clone(),
+        };
+        atomic_write(&self.session_filename, to_vec(&cached)?).await?;
+        Ok(())
+    }
+    async fn clear_credentials(&self) -> Result<()> {
+        // forget the session, but the username and password still come from the environment
+        *self.session_id.lock().unwrap() = None;
+        if try_exists(&self.session_filename).await? {
+            remove_file(&self.session_filename).await?;
+        }
+        warn!("The session was cleared, but the username and password still come from USACO_USERNAME. Unset it to stay logged out");
+        Ok(())
+    }
+    async fn get_credentials(&self) -> Result<Option<UsacoCredentials>> {
+        Ok(Some(UsacoCredentials {
+            username: self.username.clone(),
+            password: Some(self.get_password().await?.clone()),
+            session_id: self.get_session_id().await?,
+        }))
+    }
+    fn is_secure(&self) -> bool {
+        true
+    }
+}
+
+/// Current version of the JSON secret payload
+/// Version 1 was the legacy `session_id:password` string
+#[cfg(target_os = "linux")]
+const SECRET_VERSION: u32 = 2;
+
+/// JSON payload stored as the secret-service secret
+#[cfg(target_os = "linux")]
+#[derive(Serialize, Deserialize)]
+struct SecretPayload {
+    version: u32,
+    session_id: String,
+    #[serde(default)]
+    password: Option<String>,
+}
+
 /// Encrypted cred storage provider using the Linux secret-service D-Bus API
 #[cfg(target_os = "linux")]
 pub struct CredentialStorageSecretService {
@@ -125,10 +307,54 @@ impl CredentialStorageSecretService {
         Ok(self.session.get_default_collection().await?)
     }
 
-    async fn get_item<'a>(&self, collection: &'a Collection<'a>) -> Result<Option<Item<'a>>> {
+    /// get all usaco.org items, most recently modified first
+    async fn get_items<'a>(&self, collection: &'a Collection<'a>) -> Result<Vec<Item<'a>>> {
         let attrs = HashMap::from([("service", "usaco.org")]);
-        // get first result
-        Ok(collection.search_items(attrs).await?.into_iter().next())
+        let items = collection.search_items(attrs).await?;
+        let mut items_modified = Vec::with_capacity(items.len());
+        for item in items {
+            let modified = item.get_modified().await?;
+            items_modified.push((modified, item));
+        }
+        items_modified.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        Ok(items_modified.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// delete all usaco.org items (including ones for other usernames)
+    async fn delete_items(&self, collection: &Collection<'_>) -> Result<()> {
+        for item in self.get_items(collection).await? {
+            item.delete().await?;
+        }
+        Ok(())
+    }
+
+    /// parse a secret, falling back to the legacy `session_id:password` format
+    /// returns the payload and whether it needs to be migrated
+    fn parse_secret(secret: &[u8]) -> Result<(SecretPayload, bool)> {
+        // anything that is JSON is a versioned payload, never the legacy format
+        if let Ok(value) = from_slice::<serde_json::Value>(secret) {
+            return match value.get("version").and_then(|v| v.as_u64()) {
+                Some(version) if version != SECRET_VERSION as u64 => {
+                    Err(CredentialStorageError::UnsupportedVersion(version))
+                }
+                _ => Ok((serde_json::from_value(value)?, false)),
+            };
+        }
+
+        let secret =
+            std::str::from_utf8(secret).map_err(|_| CredentialStorageError::InvalidPassword)?;
+        let (session_id, password) = secret
+            .split_once(':')
+            .ok_or(CredentialStorageError::InvalidPassword)?;
+        Ok((
+            SecretPayload {
+                version: SECRET_VERSION,
+                session_id: session_id.into(),
+                // session-only logins store an empty password
+                password: (!password.is_empty()).then(|| password.into()),
+            },
+            true,
+        ))
     }
 }
 
@@ -138,63 +364,62 @@ impl CredentialStorage for CredentialStorageSecretService {
     async fn get_credentials(&self) -> Result<Option<UsacoCredentials>> {
         debug!("Loading credentials");
         let coll = self.get_collection().await?;
-        let result = self.get_item(&coll).await?;
-
-        // parse this item
-        Ok(if let Some(result) = result {
-            let mut result_attrs = result.get_attributes().await?;
-            let username = result_attrs
-                .remove("username")
-                .ok_or(CredentialStorageError::MissingUsername)?;
-            let secret = String::from_utf8(result.get_secret().await?)
-                .map_err(|_| CredentialStorageError::InvalidPassword)?;
-
-            let split_point = secret
-                .find(':')
-                .ok_or(CredentialStorageError::InvalidPassword)?;
-
-            let session_id = &secret[..split_point];
-            let password = &secret[split_point + 1..];
-
-            Some(UsacoCredentials {
-                username,
-                password: password.into(),
-                session_id: session_id.into(),
-            })
-        } else {
-            None
-        })
-    }
+        let mut items = self.get_items(&coll).await?.into_iter();
 
-    async fn clear_credentials(&self) -> Result<()> {
-        let coll = self.get_collection().await?;
-        let result = self.get_item(&coll).await?;
+        // parse the newest item
+        let Some(result) = items.next() else {
+            return Ok(None);
+        };
+        // older versions left an item behind for every username
+        for stale in items {
+            debug!("Removing stale credentials");
+            stale.delete().await?;
+        }
 
-        if let Some(result) = result {
-            result.delete().await?;
+        let mut result_attrs = result.get_attributes().await?;
+        let username = result_attrs
+            .remove("username")
+            .ok_or(CredentialStorageError::MissingUsername)?;
+        let (payload, needs_migration) = Self::parse_secret(&result.get_secret().await?)?;
+
+        let creds = UsacoCredentials {
+            username,
+            password: payload.password,
+            session_id: payload.session_id,
+        };
+        if needs_migration {
+            debug!("Migrating legacy credentials");
+            self.store_credentials(&creds).await?;
         }
+        Ok(Some(creds))
+    }
 
-        Ok(())
+    async fn clear_credentials(&self) -> Result<()> {
+        let coll = self.get_collection().await?;
+        self.delete_items(&coll).await
     }
 
     async fn store_credentials(&self, creds: &UsacoCredentials) -> Result<()> {
         debug!("saving credentials");
         let coll = self.get_collection().await?;
 
+        // `replace` only matches identical attributes, so remove items for other usernames
+        self.delete_items(&coll).await?;
+
         let attrs = HashMap::from([("service", "usaco.org"), ("username", &creds.username)]);
+        let payload = SecretPayload {
+            version: SECRET_VERSION,
+            session_id: creds.session_id.clone(),
+            password: creds.password.clone(),
+        };
 
         // add this item to the secret store
         coll.create_item(
             &format!("Credentials for '{}' on 'usaco.org'", &creds.username),
             attrs,
-            &[
-                creds.session_id.as_bytes(),
-                &[0x3a],
-                creds.password.as_bytes(),
-            ]
-            .concat(),
+            &to_vec(&payload)?,
             true,
-            "text/plain",
+            "application/json",
         )
         .await?;
 
@@ -4,7 +4,7 @@ use cookie::Cookie;
 use log::debug;
 use reqwest::{header::COOKIE, RequestBuilder};
 use scraper::{Html, Selector};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::credential_storage::UsacoCredentials;
 
@@ -15,6 +15,7 @@ struct LoginResponse {
     code: u8,
 }
 
+#[derive(Serialize)]
 pub struct UserInfo {
     pub username: String,
     pub email: String,
@@ -25,16 +26,23 @@ pub struct UserInfo {
 
 impl HttpClient {
     /// create a new session with a new login
-    pub async fn login(&self, username: String, password: String) -> Result<()> {
+    /// if `store_password` is false, only the session ID is saved
+    pub async fn login(
+        &self,
+        username: String,
+        password: String,
+        store_password: bool,
+    ) -> Result<()> {
         debug!("Login with {}", username);
         let form_data = HashMap::from([("uname", &username), ("password", &password)]);
 
         let res = self
-            .client
-            .post("https://usaco.org/current/tpcm/login-session.php")
-            .form(&form_data)
-            .header("X-Requested-With", "XMLHttpRequest")
-            .send()
+            .send(
+                self.client
+                    .post(self.url("current/tpcm/login-session.php"))
+                    .form(&form_data)
+                    .header("X-Requested-With", "XMLHttpRequest"),
+            )
             .await?;
 
         // parse the session ID cookie
@@ -51,7 +59,7 @@ impl HttpClient {
                 if let Some(session_id) = session_id {
                     let creds = UsacoCredentials {
                         username,
-                        password,
+                        password: store_password.then_some(password),
                         session_id,
                     };
 
@@ -71,15 +79,24 @@ impl HttpClient {
         let creds = self.cred_storage.get_credentials().await?;
         if let Some(mut creds) = creds {
             debug!("Refresh login for {}", creds.username);
-            let form_data =
-                HashMap::from([("uname", &creds.username), ("password", &creds.password)]);
+            let password = match &creds.password {
+                Some(password) => password.clone(),
+                // session-only login: ask for the password again
+                None => self
+                    .password_prompt
+                    .as_ref()
+                    .and_then(|prompt| prompt(&creds.username))
+                    .ok_or(HttpClientError::PasswordRequired)?,
+            };
+            let form_data = HashMap::from([("uname", &creds.username), ("password", &password)]);
 
             let res = self
-                .client
-                .post("https://usaco.org/current/tpcm/login-session.php")
-                .form(&form_data)
-                .header("X-Requested-With", "XMLHttpRequest")
-                .send()
+                .send(
+                    self.client
+                        .post(self.url("current/tpcm/login-session.php"))
+                        .form(&form_data)
+                        .header("X-Requested-With", "XMLHttpRequest"),
+                )
                 .await?;
 
             // parse the session ID cookie (not required for this one)
@@ -116,12 +133,11 @@ impl HttpClient {
         creds: &UsacoCredentials,
     ) -> Result<String> {
         debug!("Making request {:?} with session {}", req, creds.session_id);
-        let res = req
-            .header(
+        let res = self
+            .send(req.header(
                 COOKIE,
                 Cookie::new("PHPSESSID", &creds.session_id).to_string(),
-            )
-            .send()
+            ))
             .await?;
 
         let body = res.text().await?;
@@ -154,10 +170,7 @@ impl HttpClient {
     /// get account info
     pub async fn get_user_info(&self) -> Result<UserInfo> {
         let res = self
-            .authed_request_retry(
-                self.client
-                    .get("https://usaco.org/index.php?page=editaccount"),
-            )
+            .authed_request_retry(self.client.get(self.url("index.php?page=editaccount")))
             .await?;
 
         let doc = Html::parse_document(&res);
@@ -0,0 +1,94 @@
+use std::sync::LazyLock;
+
+use log::debug;
+use regex::Regex;
+use scraper::{ElementRef, Html};
+
+use super::{Division, HttpClient, HttpClientError, Result, REDIRECT_RE};
+
+static CONTEST_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)^(\d{4}) (US Open|\w+)(?: Contest)?$"#).unwrap());
+static CONTEST_SLUG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^([a-z]+\d{2})(?:results)?$"#).unwrap());
+static PROBLEM_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"viewproblem2&cpid=(\d+)"#).unwrap());
+static DIVISION_HEADING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\b(bronze|silver|gold|platinum)\b"#).unwrap());
+
+/// Convert a contest name into the name of its results page
+/// Accepts "2023 December Contest", "2024 US Open", or the short form "dec23"
+fn contest_results_page(contest: &str) -> Option<String> {
+    let contest = contest.trim();
+    if let Some(caps) = CONTEST_SLUG_RE.captures(contest) {
+        return Some(format!("{}results", &caps[1]));
+    }
+
+    let caps = CONTEST_NAME_RE.captures(contest)?;
+    let year = &caps[1][2..];
+    let month = caps[2].to_lowercase();
+    let month = if month == "us open" {
+        "open"
+    } else {
+        month.get(..3)?
+    };
+    Some(format!("{}{}results", month, year))
+}
+
+impl HttpClient {
+    /// Get the IDs of all problems in a contest
+    /// The division is None if it couldn't be determined from the results page
+    pub async fn get_contest_problems(
+        &self,
+        contest: &str,
+    ) -> Result<Vec<(Option<Division>, u64)>> {
+        let page = contest_results_page(contest).ok_or(HttpClientError::ContestNotFound)?;
+        debug!("Fetching contest results page {}", page);
+        let res = self
+            .send(
+                self.client
+                    .get(self.url(&format!("index.php?page={}", page))),
+            )
+            .await?;
+        let body = res.text().await?;
+        if REDIRECT_RE.find(&body).is_some() {
+            return Err(HttpClientError::ContestNotFound);
+        }
+
+        let doc = Html::parse_document(&body);
+        let mut division = None;
+        let mut problems: Vec<(Option<Division>, u64)> = vec![];
+
+        // walk the page in order: each division's problems come after its heading
+        for node in doc.root_element().descendants() {
+            let Some(el) = ElementRef::wrap(node) else {
+                continue;
+            };
+            match el.value().name() {
+                "h1" | "h2" | "h3" | "h4" => {
+                    let text = el.text().collect::<String>();
+                    if let Some(caps) = DIVISION_HEADING_RE.captures(&text) {
+                        division = Division::from_str(&caps[1]);
+                    }
+                }
+                "a" => {
+                    let id = el
+                        .attr("href")
+                        .and_then(|href| PROBLEM_LINK_RE.captures(href))
+                        .and_then(|caps| caps[1].parse().ok());
+                    if let Some(id) = id {
+                        if !problems.iter().any(|(_, p)| *p == id) {
+                            problems.push((division, id));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if problems.is_empty() {
+            Err(HttpClientError::ContestNotFound)
+        } else {
+            Ok(problems)
+        }
+    }
+}
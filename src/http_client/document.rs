@@ -0,0 +1,288 @@
+use regex::Regex;
+use scraper::{ElementRef, Node};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+static LATEX_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\$(.*?)\$"#).unwrap());
+static WS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\s+"#).unwrap());
+
+/// A parsed problem statement or writeup
+/// Renderers for each output format consume this instead of the HTML
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(from = "DocumentRepr")]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Block {
+    Heading(Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    /// each item is a line of inline content
+    List(Vec<Vec<Inline>>),
+    /// preformatted text, such as sample cases and code
+    Pre(String),
+    /// `src` is as it appears in the HTML, so it may be relative
+    Image {
+        src: String,
+        alt: Option<String>,
+    },
+    /// the "Problem credits: ..." line
+    Credits(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Inline {
+    Text(String),
+    /// TeX source without the surrounding `$`
+    Math(String),
+    Bold(Vec<Inline>),
+    Italic(Vec<Inline>),
+    Code(String),
+    Link {
+        href: String,
+        content: Vec<Inline>,
+    },
+}
+
+/// Older caches stored descriptions as pre-rendered ANSI strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DocumentRepr {
+    Blocks { blocks: Vec<Block> },
+    Legacy(String),
+}
+
+impl From<DocumentRepr> for Document {
+    fn from(repr: DocumentRepr) -> Self {
+        match repr {
+            DocumentRepr::Blocks { blocks } => Self { blocks },
+            // keep the text readable until the problem is re-parsed
+            DocumentRepr::Legacy(text) => Self {
+                blocks: console::strip_ansi_codes(&text)
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| Block::Paragraph(vec![Inline::Text(line.trim().into())]))
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl Document {
+    /// Parse the contents of an HTML element
+    pub fn from_html(el: ElementRef<'_>) -> Self {
+        let mut builder = DocumentBuilder::default();
+        builder.visit_children(el);
+        builder.flush();
+        Self {
+            blocks: builder.blocks,
+        }
+    }
+
+    /// Make relative image and link URLs absolute
+    pub fn resolve_urls(&mut self, resolve: impl Fn(&str) -> String) {
+        for block in &mut self.blocks {
+            match block {
+                Block::Image { src, .. } => *src = resolve(src),
+                Block::Heading(inlines) | Block::Paragraph(inlines) => {
+                    resolve_inline_urls(inlines, &resolve)
+                }
+                Block::List(items) => items
+                    .iter_mut()
+                    .for_each(|item| resolve_inline_urls(item, &resolve)),
+                Block::Pre(_) | Block::Credits(_) => {}
+            }
+        }
+    }
+}
+
+fn resolve_inline_urls(inlines: &mut [Inline], resolve: &impl Fn(&str) -> String) {
+    for inline in inlines {
+        match inline {
+            Inline::Link { href, content } => {
+                *href = resolve(href);
+                resolve_inline_urls(content, resolve);
+            }
+            Inline::Bold(content) | Inline::Italic(content) => {
+                resolve_inline_urls(content, resolve)
+            }
+            Inline::Text(_) | Inline::Math(_) | Inline::Code(_) => {}
+        }
+    }
+}
+
+impl Inline {
+    /// Text content without any formatting. Math keeps its TeX source
+    pub fn plain_text(inlines: &[Inline]) -> String {
+        let mut text = String::new();
+        for inline in inlines {
+            match inline {
+                Self::Text(s) | Self::Code(s) => text.push_str(s),
+                Self::Math(tex) => {
+                    text.push('$');
+                    text.push_str(tex);
+                    text.push('$');
+                }
+                Self::Bold(content) | Self::Italic(content) | Self::Link { content, .. } => {
+                    text.push_str(&Self::plain_text(content))
+                }
+            }
+        }
+        text
+    }
+}
+
+#[derive(Default)]
+struct DocumentBuilder {
+    blocks: Vec<Block>,
+    /// inline content of the paragraph being built
+    inlines: Vec<Inline>,
+}
+
+impl DocumentBuilder {
+    /// finish the current paragraph
+    fn flush(&mut self) {
+        let mut inlines = std::mem::take(&mut self.inlines);
+        trim_inlines(&mut inlines);
+        if inlines.is_empty() {
+            return;
+        }
+        let text = Inline::plain_text(&inlines);
+        if text.starts_with("Problem credits") {
+            self.blocks.push(Block::Credits(text));
+        } else {
+            self.blocks.push(Block::Paragraph(inlines));
+        }
+    }
+
+    fn visit_children(&mut self, el: ElementRef<'_>) {
+        for c in el.children() {
+            match c.value() {
+                Node::Text(text) => push_text(&mut self.inlines, text),
+                Node::Element(e) => {
+                    let c_el = ElementRef::wrap(c).unwrap();
+                    match e.name() {
+                        "script" | "style" => {}
+                        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                            self.flush();
+                            let mut inlines = parse_inlines(c_el);
+                            trim_inlines(&mut inlines);
+                            if !inlines.is_empty() {
+                                self.blocks.push(Block::Heading(inlines));
+                            }
+                        }
+                        "ul" | "ol" => {
+                            self.flush();
+                            let items = c_el
+                                .child_elements()
+                                .map(|li| {
+                                    let mut inlines = parse_inlines(li);
+                                    trim_inlines(&mut inlines);
+                                    inlines
+                                })
+                                .filter(|inlines| !inlines.is_empty())
+                                .collect::<Vec<_>>();
+                            if !items.is_empty() {
+                                self.blocks.push(Block::List(items));
+                            }
+                        }
+                        "pre" => {
+                            self.flush();
+                            self.blocks.push(Block::Pre(c_el.text().collect()));
+                        }
+                        "img" => {
+                            self.flush();
+                            if let Some(src) = e.attr("src") {
+                                self.blocks.push(Block::Image {
+                                    src: src.into(),
+                                    alt: e.attr("alt").map(String::from),
+                                });
+                            }
+                        }
+                        "br" => self.flush(),
+                        "p" | "div" | "center" | "blockquote" | "section" | "table" | "tr"
+                        | "td" | "body" => {
+                            self.flush();
+                            self.visit_children(c_el);
+                            self.flush();
+                        }
+                        _ => self.inlines.extend(parse_inline(c_el)),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// parse an element's children as inline content, flattening any block elements
+fn parse_inlines(el: ElementRef<'_>) -> Vec<Inline> {
+    let mut inlines = vec![];
+    for c in el.children() {
+        match c.value() {
+            Node::Text(text) => push_text(&mut inlines, text),
+            Node::Element(_) => inlines.extend(parse_inline(ElementRef::wrap(c).unwrap())),
+            _ => {}
+        }
+    }
+    inlines
+}
+
+/// parse a single inline element
+fn parse_inline(el: ElementRef<'_>) -> Vec<Inline> {
+    match el.value().name() {
+        "script" | "style" | "img" => vec![],
+        "br" => vec![Inline::Text(" ".into())],
+        "strong" | "b" => vec![Inline::Bold(parse_inlines(el))],
+        "em" | "i" => vec![Inline::Italic(parse_inlines(el))],
+        "code" | "tt" => vec![Inline::Code(el.text().collect())],
+        "a" => match el.attr("href") {
+            Some(href) => vec![Inline::Link {
+                href: href.into(),
+                content: parse_inlines(el),
+            }],
+            None => parse_inlines(el),
+        },
+        _ => parse_inlines(el),
+    }
+}
+
+/// add text to inline content, splitting out `$...$` math
+fn push_text(inlines: &mut Vec<Inline>, text: &str) {
+    let text = WS_RE.replace_all(text, " ");
+    let mut last = 0;
+    for caps in LATEX_RE.captures_iter(&text) {
+        let m = caps.get(0).unwrap();
+        push_plain(inlines, &text[last..m.start()]);
+        inlines.push(Inline::Math(caps[1].trim().into()));
+        last = m.end();
+    }
+    push_plain(inlines, &text[last..]);
+}
+
+/// add plain text, merging it with the previous text node
+fn push_plain(inlines: &mut Vec<Inline>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match inlines.last_mut() {
+        // whitespace was only collapsed within each text node
+        Some(Inline::Text(prev)) if prev.ends_with(' ') => prev.push_str(text.trim_start()),
+        Some(Inline::Text(prev)) => prev.push_str(text),
+        _ => inlines.push(Inline::Text(text.into())),
+    }
+}
+
+/// remove whitespace at the start and end of inline content
+fn trim_inlines(inlines: &mut Vec<Inline>) {
+    if let Some(Inline::Text(text)) = inlines.first_mut() {
+        *text = text.trim_start().into();
+    }
+    if let Some(Inline::Text(text)) = inlines.last_mut() {
+        *text = text.trim_end().into();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+}
@@ -1,10 +1,13 @@
 mod account;
+mod contest;
+mod document;
 mod problem;
+mod retry;
 //mod solution;
 
 use std::{
     sync::{Arc, LazyLock},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use regex::Regex;
@@ -16,7 +19,12 @@ use zip::result::ZipError;
 use crate::credential_storage::{CredentialStorage, CredentialStorageError};
 
 pub use account::UserInfo;
-pub use problem::{IoMode, Problem};
+pub use document::{Block, Document, Inline};
+pub use problem::{
+    parse_official_test_cases, reparse_problem, IoMode, Problem, RawProblemHtml, TestCase,
+    PARSER_VERSION,
+};
+use retry::RateLimiter;
 
 #[derive(Error, Debug)]
 pub enum HttpClientError {
@@ -34,11 +42,18 @@ pub enum HttpClientError {
 
     #[error("Session expired")]
     SessionExpired,
+    #[error("Session expired and no password is stored. Run `usaco auth login` again")]
+    PasswordRequired,
     #[error("Invalid username or password!")]
     InvalidUsernamePassword,
 
     #[error("Problem not found")]
     ProblemNotFound,
+    #[error("Contest not found. Use a name like \"2023 December Contest\" or \"dec23\"")]
+    ContestNotFound,
+
+    #[error("Offline mode is enabled and this needs network access. Run without --offline or `usaco preferences set offline false`")]
+    Offline,
 
     #[error("Unexpected or malformed response from USACO backend: {0}")]
     UnexpectedResponse(&'static str),
@@ -95,16 +110,25 @@ impl Division {
             Self::Platinum => "platinum",
         }
     }
+    /// Capitalized division name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bronze => "Bronze",
+            Self::Silver => "Silver",
+            Self::Gold => "Gold",
+            Self::Platinum => "Platinum",
+        }
+    }
     /// Color the division with the division colors
     pub fn to_ansi(&self) -> String {
-        let div_format = match self {
-            Self::Gold => ("Gold", "246;221;138"),
-            Self::Silver => ("Silver", "199;199;199"),
-            Self::Bronze => ("Bronze", "232;175;140"),
-            Self::Platinum => ("Platinum", "207;211;180"),
+        let color = match self {
+            Self::Gold => "246;221;138",
+            Self::Silver => "199;199;199",
+            Self::Bronze => "232;175;140",
+            Self::Platinum => "207;211;180",
         };
 
-        format!("\x1b[38;2;{}m{}\x1b[0m", div_format.1, div_format.0)
+        format!("\x1b[38;2;{}m{}\x1b[0m", color, self.name())
     }
     /// Return all division names in lowercase
     pub fn get_all() -> [&'static str; 4] {
@@ -112,27 +136,103 @@ impl Division {
     }
 }
 
+/// Official USACO site
+pub const DEFAULT_BASE_URL: &str = "https://usaco.org";
+
+/// Settings for the HTTP client
+#[derive(Debug, Clone)]
+pub struct HttpClientOptions {
+    /// Base URL all requests go through (mirrors, local mock servers)
+    pub base_url: String,
+    /// Timeout for connecting and for each read, so slow but steady downloads still finish
+    pub timeout: Duration,
+    /// How many times to retry idempotent requests
+    pub max_retries: u32,
+    /// Maximum requests per second. 0 for unlimited
+    pub rate_limit: u32,
+    /// Never touch the network
+    pub offline: bool,
+}
+
+impl Default for HttpClientOptions {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.into(),
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            rate_limit: 4,
+            offline: false,
+        }
+    }
+}
+
+/// Called with the username when a session-only login needs the password again
+pub type PasswordPrompt = Arc<dyn Fn(&str) -> Option<String>>;
+
 #[derive(Clone)]
 pub struct HttpClient {
     cred_storage: Arc<dyn CredentialStorage>,
     client: Client,
+    password_prompt: Option<PasswordPrompt>,
+    /// without a trailing slash
+    base_url: String,
+    max_retries: u32,
+    /// overrides the client's timeout for each request
+    request_timeout: Option<Duration>,
+    /// shared between clones
+    rate_limiter: Arc<RateLimiter>,
+    offline: bool,
 }
 
 impl HttpClient {
-    pub fn init(cred_storage: Arc<dyn CredentialStorage>) -> Self {
+    pub fn init(cred_storage: Arc<dyn CredentialStorage>, options: HttpClientOptions) -> Self {
         let client = Client::builder()
             .user_agent(format!("github.com/grimsteel/usaco-cli v{} by contact [dot] 61uq6rb3dw [at] kameswar [dot] com", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(options.timeout)
+            .read_timeout(options.timeout)
             .build().unwrap();
         Self {
             client,
             cred_storage,
+            password_prompt: None,
+            base_url: options.base_url.trim_end_matches('/').into(),
+            max_retries: options.max_retries,
+            request_timeout: None,
+            rate_limiter: Arc::new(RateLimiter::new(options.rate_limit)),
+            offline: options.offline,
         }
     }
 
+    /// make a path (relative to the site root) absolute
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    /// URL of the problem view page
+    pub fn problem_url(&self, problem_id: u64) -> String {
+        self.url(&format!("index.php?page=viewproblem2&cpid={}", problem_id))
+    }
+
+    /// A copy of the client for requests that can fail quietly: no retries and a short timeout
+    pub fn without_retries(&self, timeout: Duration) -> Self {
+        Self {
+            max_retries: 0,
+            request_timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Set the callback used to ask for the password when a session-only login expires
+    pub fn with_password_prompt(mut self, prompt: PasswordPrompt) -> Self {
+        self.password_prompt = Some(prompt);
+        self
+    }
+
     /// test and time connection to usaco.org
     pub async fn ping(&self) -> Result<Option<u128>> {
+        let req = self.client.get(self.url(""));
         let start = Instant::now();
-        let res = self.client.get("https://usaco.org").send().await?;
+        let res = self.send_once(req).await?;
         let time = start.elapsed().as_millis();
         Ok(if res.status() == StatusCode::OK {
             Some(time)
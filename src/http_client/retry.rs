@@ -0,0 +1,128 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use log::{debug, warn};
+use reqwest::{Method, RequestBuilder, Response};
+use tokio::{
+    sync::Mutex,
+    time::{sleep, sleep_until, Instant},
+};
+
+use super::{HttpClient, HttpClientError, Result};
+
+/// delay before the first retry. doubles every attempt
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Spaces requests out so we don't hammer the USACO servers
+pub struct RateLimiter {
+    interval: Duration,
+    /// earliest time the next request can be sent
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    /// `per_second` of 0 disables rate limiting
+    pub fn new(per_second: u32) -> Self {
+        let interval = if per_second == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs(1) / per_second
+        };
+        Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// wait until we're allowed to send another request
+    async fn wait(&self) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + self.interval;
+    }
+}
+
+/// exponential backoff with up to 50% random jitter
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    // no need for a full rng
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = delay.mul_f64((random % 1000) as f64 / 2000.0);
+    delay + jitter
+}
+
+impl HttpClient {
+    /// send a single request, without retrying
+    pub(super) async fn send_once(&self, req: RequestBuilder) -> Result<Response> {
+        if self.offline {
+            return Err(HttpClientError::Offline);
+        }
+        let (client, req) = req.build_split();
+        let mut req = req?;
+        if self.request_timeout.is_some() {
+            *req.timeout_mut() = self.request_timeout;
+        }
+        self.rate_limiter.wait().await;
+        Ok(client.execute(req).await?)
+    }
+
+    /// send a request, retrying idempotent requests on network errors and 5xx responses
+    pub(super) async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        if self.offline {
+            return Err(HttpClientError::Offline);
+        }
+        let (client, req) = req.build_split();
+        let mut req = req?;
+        if self.request_timeout.is_some() {
+            *req.timeout_mut() = self.request_timeout;
+        }
+        // don't resend logins
+        let idempotent = matches!(*req.method(), Method::GET | Method::HEAD);
+
+        let mut attempt = 0;
+        loop {
+            // bodies are always in memory, so this can't fail
+            let attempt_req = req.try_clone().unwrap();
+            self.rate_limiter.wait().await;
+
+            let can_retry = idempotent && attempt < self.max_retries;
+            match client.execute(attempt_req).await {
+                Ok(res) if res.status().is_server_error() && can_retry => {
+                    warn!(
+                        "{} returned {}, retrying (attempt {}/{})",
+                        req.url(),
+                        res.status(),
+                        attempt + 1,
+                        self.max_retries
+                    );
+                }
+                Err(e) if (e.is_timeout() || e.is_connect() || e.is_request()) && can_retry => {
+                    warn!(
+                        "{}, retrying (attempt {}/{})",
+                        e,
+                        attempt + 1,
+                        self.max_retries
+                    );
+                }
+                r => return Ok(r?),
+            }
+
+            let delay = backoff(attempt);
+            debug!("Waiting {}ms before retrying", delay.as_millis());
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
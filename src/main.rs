@@ -1,3 +1,4 @@
+mod atomic_write;
 mod cli;
 mod credential_storage;
 mod http_client;
@@ -1,18 +1,28 @@
-use super::http_client::Problem;
+use super::atomic_write::{atomic_write, FileLock};
+use super::http_client::{reparse_problem, Problem, RawProblemHtml, PARSER_VERSION};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use clap::ValueEnum;
 use directories::ProjectDirs;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use indexmap::IndexMap;
-use log::debug;
+use log::{debug, warn};
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::{Ref, RefCell, RefMut},
-    path::PathBuf,
+    cell::{Cell, Ref, RefCell, RefMut},
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 use thiserror::Error;
-use tokio::fs::{create_dir_all, read, try_exists, write};
+use tokio::fs::{read, remove_file, try_exists};
 
 const PREF_FILE_NAME: &'static str = "config.json";
 const CACHE_FILE_NAME: &'static str = "problem-cache.json";
+const TEST_DATA_DIR_NAME: &str = "test-data";
+const FAILED_CASES_FILE_NAME: &str = "failed-cases.json";
+pub const DEFAULT_CACHE_SIZE: usize = 10;
+/// how often to check for released data on problems from ongoing contests
+const RELEASED_DATA_REFRESH_SECS: u64 = 6 * 60 * 60;
 
 #[derive(Error, Debug)]
 pub enum PreferencesError {
@@ -71,15 +81,161 @@ pub struct Preferences {
     pub preferred_language: Language,
     #[serde(default)]
     pub solutions_dir: Option<PathBuf>,
+    /// USACO site to use instead of usaco.org. Overridden by `USACO_BASE_URL`
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Timeout in seconds for connecting and for each read
+    #[serde(default)]
+    pub request_timeout: Option<u64>,
+    /// How many times to retry failed requests
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// Maximum requests per second (0 for unlimited)
+    #[serde(default)]
+    pub rate_limit: Option<u32>,
+    /// Serve everything from the cache and never touch the network
+    #[serde(default)]
+    pub offline: bool,
+    /// Maximum number of cached problems (0 for unlimited)
+    #[serde(default)]
+    pub cache_size: Option<usize>,
+}
+
+/// A problem in the cache, along with cache metadata
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CachedProblem {
+    /// flattened so caches from older versions still load
+    #[serde(flatten)]
+    pub problem: Problem,
+    /// pinned problems are never evicted
+    #[serde(default)]
+    pub pinned: bool,
+    /// unix timestamp of when the problem was fetched. 0 if unknown
+    #[serde(default)]
+    pub fetched_at: u64,
+    /// raw HTML the problem was parsed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_html: Option<CompressedHtml>,
+    /// version of the parser that produced `problem`. 0 if unknown
+    #[serde(default)]
+    pub parser_version: u32,
+}
+
+/// Deflate compressed and base64 encoded HTML
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressedHtml {
+    problem: String,
+    #[serde(default)]
+    writeup: Option<String>,
+}
+
+fn compress(html: &str) -> String {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // writing to a vec can't fail
+    encoder.write_all(html.as_bytes()).unwrap();
+    BASE64_STANDARD.encode(encoder.finish().unwrap())
 }
 
-type ProblemCache = IndexMap<u64, Problem>;
+fn decompress(data: &str) -> Option<String> {
+    let compressed = BASE64_STANDARD.decode(data).ok()?;
+    let mut html = String::new();
+    DeflateDecoder::new(&compressed[..])
+        .read_to_string(&mut html)
+        .ok()?;
+    Some(html)
+}
+
+impl CompressedHtml {
+    fn new(raw: &RawProblemHtml) -> Self {
+        Self {
+            problem: compress(&raw.problem),
+            writeup: raw.writeup.as_deref().map(compress),
+        }
+    }
+
+    fn decompress(&self) -> Option<RawProblemHtml> {
+        Some(RawProblemHtml {
+            problem: decompress(&self.problem)?,
+            writeup: match &self.writeup {
+                Some(writeup) => Some(decompress(writeup)?),
+                None => None,
+            },
+        })
+    }
+}
+
+impl CachedProblem {
+    /// released data (writeup, official test data) may have come out since this was fetched,
+    /// or the problem was parsed by an older parser and can't be re-parsed locally
+    pub fn needs_refresh(&self) -> bool {
+        let missing_released_data = self.problem.released_data.is_none()
+            && unix_now().saturating_sub(self.fetched_at) > RELEASED_DATA_REFRESH_SECS;
+        let outdated = self.parser_version != PARSER_VERSION && self.raw_html.is_none();
+        missing_released_data || outdated
+    }
+
+    /// re-parse the problem from the raw HTML if it was parsed by a different parser version
+    /// returns whether anything changed
+    fn reparse(&mut self) -> bool {
+        if self.parser_version == PARSER_VERSION {
+            return false;
+        }
+        let Some(raw) = self.raw_html.as_ref().and_then(|r| r.decompress()) else {
+            return false;
+        };
+        match reparse_problem(&self.problem, &raw) {
+            Ok(problem) => {
+                debug!("Re-parsed cached problem {}", problem.id);
+                self.problem = problem;
+                self.parser_version = PARSER_VERSION;
+                true
+            }
+            Err(e) => {
+                debug!(
+                    "Could not re-parse cached problem {}: {}",
+                    self.problem.id, e
+                );
+                false
+            }
+        }
+    }
+}
+
+type ProblemCache = IndexMap<u64, CachedProblem>;
 
 #[derive(Debug)]
 pub struct DataStore {
     preferences: RefCell<Preferences>,
+    /// preferences as they were last loaded or saved, to tell which ones this process changed
+    saved_preferences: RefCell<serde_json::Value>,
     dirs: ProjectDirs,
     problem_cache: RefCell<ProblemCache>,
+    /// problems read since the cache was last saved, least recent first
+    touched: RefCell<Vec<u64>>,
+    /// problems were re-parsed but haven't been saved yet
+    cache_dirty: Cell<bool>,
+}
+
+/// read the problem cache, discarding it if it's corrupt
+/// returns the cache and whether any problems were re-parsed
+async fn load_cache(path: &Path) -> Result<(ProblemCache, bool)> {
+    let mut problem_cache: ProblemCache = match read(path).await {
+        // the cache can always be rebuilt, so don't fail if it's corrupt
+        Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+            warn!("Discarding corrupt problem cache {}: {}", path.display(), e);
+            ProblemCache::new()
+        }),
+        // empty cache
+        Err(e) if e.kind() == ErrorKind::NotFound => ProblemCache::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    // re-parse problems cached by older parser versions
+    let mut reparsed = false;
+    for problem in problem_cache.values_mut() {
+        reparsed |= problem.reparse();
+    }
+    Ok((problem_cache, reparsed))
 }
 
 impl DataStore {
@@ -89,40 +245,56 @@ impl DataStore {
     pub async fn new(dirs: ProjectDirs) -> Result<Self> {
         // load prefs
         let config_path = dirs.config_dir().join(PREF_FILE_NAME);
-        let preferences = if try_exists(&config_path).await? {
+        let preferences: Preferences = if try_exists(&config_path).await? {
             debug!("Loading preferences from {}", config_path.display());
-            RefCell::new(serde_json::from_slice(&read(config_path).await?)?)
+            serde_json::from_slice(&read(config_path).await?)?
         } else {
             debug!("Creating preferences at {}", config_path.display());
 
             // create in user config dir
-            create_dir_all(dirs.config_dir()).await?;
-            write(&config_path, "{}").await?;
-            RefCell::new(Preferences::default())
+            atomic_write(&config_path, "{}").await?;
+            Preferences::default()
         };
 
         // load cache
-        let problem_cache_path = dirs.cache_dir().join(CACHE_FILE_NAME);
-        let problem_cache = if try_exists(&problem_cache_path).await? {
-            RefCell::new(serde_json::from_slice(&read(problem_cache_path).await?)?)
-        } else {
-            // empty cache
-            RefCell::new(ProblemCache::new())
-        };
+        let (problem_cache, reparsed) = load_cache(&dirs.cache_dir().join(CACHE_FILE_NAME)).await?;
 
         Ok(Self {
-            preferences,
+            saved_preferences: RefCell::new(serde_json::to_value(&preferences)?),
+            preferences: RefCell::new(preferences),
             dirs,
-            problem_cache,
+            problem_cache: RefCell::new(problem_cache),
+            touched: RefCell::new(vec![]),
+            cache_dirty: Cell::new(reparsed),
         })
     }
 
+    /// Save the preferences changed by this process
+    /// Preferences another process saved in the meantime are kept and loaded
     pub async fn save_prefs(&self) -> Result<()> {
-        let lock = self.read()?;
-        let serialized = serde_json::to_vec(&*lock)?;
+        let path = self.dirs.config_dir().join(PREF_FILE_NAME);
+        let _lock = FileLock::acquire(&path).await?;
+        let on_disk: Preferences = match read(&path).await {
+            Ok(data) => serde_json::from_slice(&data)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Preferences::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut merged = serde_json::to_value(on_disk)?;
+        let current = serde_json::to_value(&*self.read()?)?;
+        let saved = self.saved_preferences.borrow().clone();
+        if let Some(current) = current.as_object() {
+            for (key, value) in current {
+                if saved.get(key) != Some(value) {
+                    merged[key] = value.clone();
+                }
+            }
+        }
+
+        let preferences: Preferences = serde_json::from_value(merged.clone())?;
         // write to config dir
-        create_dir_all(self.dirs.config_dir()).await?;
-        write(&self.dirs.config_dir().join(PREF_FILE_NAME), serialized).await?;
+        atomic_write(&path, serde_json::to_vec(&preferences)?).await?;
+        *self.write()? = preferences;
+        *self.saved_preferences.borrow_mut() = merged;
         Ok(())
     }
 
@@ -138,28 +310,53 @@ impl DataStore {
             .map_err(|_| PreferencesError::PrefsLocked)
     }
 
-    /// uses an existing borrowed problem cache for efficiency
-    async fn save_cache(&self, cache: &ProblemCache) -> Result<()> {
-        let serialized = serde_json::to_vec(cache)?;
+    fn cache_mut(&self) -> Result<RefMut<'_, ProblemCache>> {
+        self.problem_cache
+            .try_borrow_mut()
+            .map_err(|_| PreferencesError::PrefsLocked)
+    }
+
+    /// Reload the cache, change it and save it, without other processes writing in between
+    /// Problems read by this process are moved to the front first
+    async fn update_cache<T>(&self, f: impl FnOnce(&mut ProblemCache) -> T) -> Result<T> {
+        let path = self.dirs.cache_dir().join(CACHE_FILE_NAME);
+        let _lock = FileLock::acquire(&path).await?;
+        let (mut problem_cache, _) = load_cache(&path).await?;
+        for id in self.touched.borrow_mut().drain(..) {
+            if let Some(idx) = problem_cache.get_index_of(&id) {
+                problem_cache.move_index(idx, 0);
+            }
+        }
+        let result = f(&mut problem_cache);
+
         // write to cache dir
-        create_dir_all(self.dirs.cache_dir()).await?;
-        write(&self.dirs.cache_dir().join(CACHE_FILE_NAME), serialized).await?;
+        atomic_write(&path, serde_json::to_vec(&problem_cache)?).await?;
+        *self.cache_mut()? = problem_cache;
+        self.cache_dirty.set(false);
+        Ok(result)
+    }
+
+    /// save the cache if the LRU order changed since it was last saved
+    pub async fn flush_cache(&self) -> Result<()> {
+        if self.cache_dirty.get() || !self.touched.borrow().is_empty() {
+            self.update_cache(|_| ()).await?;
+        }
         Ok(())
     }
 
-    /// insert a problem into the LRU cache
-    pub async fn get_cache(&'_ self, id: u64) -> Result<Option<Ref<'_, Problem>>> {
-        let mut lock = self
-            .problem_cache
-            .try_borrow_mut()
-            .map_err(|_| PreferencesError::PrefsLocked)?;
+    /// get a problem from the LRU cache
+    /// the new LRU order is only saved by `flush_cache`
+    pub fn get_cache(&'_ self, id: u64) -> Result<Option<Ref<'_, CachedProblem>>> {
+        let mut lock = self.cache_mut()?;
         if let Some(idx) = lock.get_index_of(&id) {
             // move to position 0
-            lock.move_index(idx, 0);
+            if idx != 0 {
+                lock.move_index(idx, 0);
+                self.touched.borrow_mut().push(id);
+            }
             // reborrow as immutable
             drop(lock);
             let lock = self.get_full_cache()?;
-            self.save_cache(&*lock).await?;
             // return just the item we care about
             let problem = Ref::filter_map(lock, |l| l.get(&id)).ok();
             Ok(problem)
@@ -175,41 +372,153 @@ impl DataStore {
             .map_err(|_| PreferencesError::PrefsLocked)
     }
 
-    /// insert a problem into the LRU cache
-    pub async fn insert_cache(&self, problem: Problem) -> Result<()> {
-        let mut lock = self
-            .problem_cache
-            .try_borrow_mut()
-            .map_err(|_| PreferencesError::PrefsLocked)?;
-        lock.insert_before(0, problem.id, problem);
-        // remove old items
-        while lock.len() > 10 {
-            lock.shift_remove_index(10);
-        }
-        self.save_cache(&*lock).await?;
-        Ok(())
+    /// insert a problem into the LRU cache, pinning it if `pin` is set
+    pub async fn insert_cache(
+        &self,
+        problem: Problem,
+        raw: &RawProblemHtml,
+        pin: bool,
+    ) -> Result<()> {
+        let capacity = self.read()?.cache_size.unwrap_or(DEFAULT_CACHE_SIZE);
+        let evicted = self
+            .update_cache(|cache| {
+                // keep the pin when refetching
+                let pinned = pin || cache.get(&problem.id).is_some_and(|p| p.pinned);
+                let cached = CachedProblem {
+                    problem,
+                    pinned,
+                    fetched_at: unix_now(),
+                    raw_html: Some(CompressedHtml::new(raw)),
+                    parser_version: PARSER_VERSION,
+                };
+                cache.insert_before(0, cached.problem.id, cached);
+                // remove the least recently used unpinned items
+                let mut evicted = vec![];
+                if capacity > 0 {
+                    while cache.len() > capacity {
+                        match cache.values().rposition(|p| !p.pinned) {
+                            Some(idx) => {
+                                evicted.extend(cache.shift_remove_index(idx).map(|(id, _)| id))
+                            }
+                            // everything is pinned
+                            None => break,
+                        };
+                    }
+                }
+                evicted
+            })
+            .await?;
+        self.remove_test_data(&evicted).await
     }
 
-    /// remove items from the cache
-    pub async fn remove_cache(&self, items: Vec<u64>) -> Result<usize> {
-        let mut lock = self
-            .problem_cache
-            .try_borrow_mut()
-            .map_err(|_| PreferencesError::PrefsLocked)?;
-        let count = if items.len() > 0 {
+    /// pin or unpin items in the cache
+    /// returns the number of items changed
+    pub async fn set_pinned(&self, items: &[u64], pinned: bool) -> Result<usize> {
+        self.update_cache(|cache| {
             let mut i = 0;
-            for id in &items {
-                if lock.shift_remove(id).is_some() {
+            for id in items {
+                if let Some(problem) = cache.get_mut(id) {
+                    problem.pinned = pinned;
                     i += 1;
                 }
             }
             i
-        } else {
-            let len = lock.len();
-            lock.clear();
-            len
-        };
-        self.save_cache(&*lock).await?;
-        Ok(count)
+        })
+        .await
     }
+
+    /// remove items from the cache
+    pub async fn remove_cache(&self, items: Vec<u64>) -> Result<usize> {
+        let removed = self
+            .update_cache(|cache| {
+                if items.len() > 0 {
+                    items
+                        .into_iter()
+                        .filter(|id| cache.shift_remove(id).is_some())
+                        .collect::<Vec<_>>()
+                } else {
+                    cache.drain(..).map(|(id, _)| id).collect()
+                }
+            })
+            .await?;
+        self.remove_test_data(&removed).await?;
+        Ok(removed.len())
+    }
+
+    fn test_data_dir(&self) -> PathBuf {
+        self.dirs.cache_dir().join(TEST_DATA_DIR_NAME)
+    }
+
+    fn test_data_path(&self, id: u64) -> PathBuf {
+        self.test_data_dir().join(format!("{}.zip", id))
+    }
+
+    /// get a cached official test data zip file
+    pub async fn get_test_data(&self, id: u64) -> Result<Option<Vec<u8>>> {
+        match read(self.test_data_path(id)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// check if the official test data for a problem is cached
+    pub async fn has_test_data(&self, id: u64) -> Result<bool> {
+        Ok(try_exists(self.test_data_path(id)).await?)
+    }
+
+    /// cache an official test data zip file
+    pub async fn save_test_data(&self, id: u64, data: &[u8]) -> Result<()> {
+        let _lock = FileLock::acquire(self.test_data_dir()).await?;
+        atomic_write(self.test_data_path(id), data).await?;
+        Ok(())
+    }
+
+    /// read the failed cases of every solution
+    async fn read_failed_cases(&self) -> Result<IndexMap<String, Vec<usize>>> {
+        match read(self.dirs.cache_dir().join(FAILED_CASES_FILE_NAME)).await {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(IndexMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// get the cases that failed the last time a solution was tested
+    /// `key` identifies the solution and data set
+    pub async fn get_failed_cases(&self, key: &str) -> Result<Option<Vec<usize>>> {
+        Ok(self.read_failed_cases().await?.swap_remove(key))
+    }
+
+    /// remember the cases that failed for a solution
+    pub async fn set_failed_cases(&self, key: &str, cases: Vec<usize>) -> Result<()> {
+        let path = self.dirs.cache_dir().join(FAILED_CASES_FILE_NAME);
+        let _lock = FileLock::acquire(&path).await?;
+        let mut failed_cases = self.read_failed_cases().await?;
+        failed_cases.insert(key.into(), cases);
+        atomic_write(&path, serde_json::to_string(&failed_cases)?).await?;
+        Ok(())
+    }
+
+    /// remove test data for problems that are no longer cached
+    async fn remove_test_data(&self, ids: &[u64]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _lock = FileLock::acquire(self.test_data_dir()).await?;
+        for id in ids {
+            match remove_file(self.test_data_path(*id)).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// seconds since the unix epoch
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
 }